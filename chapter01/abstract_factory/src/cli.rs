@@ -0,0 +1,117 @@
+// Command line options and answers to the questions of the game.
+
+use std::fs::File;
+use std::io;
+use std::io::{BufRead, BufReader, IsTerminal, Write};
+
+pub const USAGE: &str = "Usage: abstract_factory [--name NAME] [--age AGE] [--world WORLD] [--seed SEED] [--script FILE] [--load FILE]";
+
+/// Answers which are not given by options are asked.
+#[derive(Debug, Default)]
+pub struct Options {
+    pub name: Option<String>,
+    pub age: Option<u32>,
+    pub world: Option<String>,
+    pub seed: Option<u64>,
+    /// File to read answers from, one answer per line, instead of stdin.
+    pub script: Option<String>,
+    /// Saved game to resume instead of starting a new one.
+    pub load: Option<String>,
+}
+
+impl Options {
+    pub fn parse(args: &[String]) -> Result<Options, String> {
+        let mut options = Options::default();
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().cloned().ok_or(format!("{} needs a value", arg));
+            match arg.as_str() {
+                "--name" => options.name = Some(value()?),
+                "--age" => {
+                    let age = value()?;
+                    options.age = Some(age.parse().map_err(|_| format!("Invalid age: {}", age))?);
+                },
+                "--world" => options.world = Some(value()?),
+                "--seed" => {
+                    let seed = value()?;
+                    options.seed = Some(seed.parse().map_err(|_| format!("Invalid seed: {}", seed))?);
+                },
+                "--script" => options.script = Some(value()?),
+                "--load" => options.load = Some(value()?),
+                _ => return Err(format!("Unknown argument: {}", arg)),
+            }
+        }
+
+        Ok(options)
+    }
+}
+
+/// Asks questions and reads answers from stdin or a script.
+pub struct Prompter {
+    input: Box<dyn BufRead>,
+    /// Answers from a script are printed as if they were typed.
+    echo: bool,
+    /// Answers come from a script or a player at a terminal.
+    interactive: bool,
+}
+
+impl Prompter {
+    pub fn stdin() -> Prompter {
+        Prompter {
+            input: Box::new(BufReader::new(io::stdin())),
+            echo: false,
+            interactive: io::stdin().is_terminal(),
+        }
+    }
+
+    pub fn script(filepath: &str) -> io::Result<Prompter> {
+        Ok(Prompter {
+            input: Box::new(BufReader::new(File::open(filepath)?)),
+            echo: true,
+            interactive: true,
+        })
+    }
+
+    /// Whether questions which are not needed to play, such as whether to save, should be asked.
+    ///
+    /// A program which pipes stdin without answering them would wait forever.
+    pub fn is_interactive(&self) -> bool {
+        self.interactive
+    }
+
+    /// Ask a question. `None` means no answer is left.
+    pub fn ask(&mut self, question: &str) -> Option<String> {
+        let mut answer = String::new();
+
+        print!("{}", question);
+        // https://stackoverflow.com/questions/37531903/how-do-i-print-output-without-a-trailing-newline-in-rust
+        io::stdout().flush().unwrap();
+        match self.input.read_line(&mut answer) {
+            Ok(0) | Err(_) => {
+                println!();
+                return None;
+            },
+            Ok(_) => {},
+        }
+
+        let answer = String::from(answer.trim_end_matches(['\n', '\r']));
+        if self.echo {
+            println!("{}", answer);
+        }
+        Some(answer)
+    }
+
+    /// Ask until the answer is parsed.
+    pub fn ask_until<T, F>(&mut self, question: &str, retry: &str, parse: F) -> Option<T>
+    where
+        F: Fn(&str) -> Option<T>,
+    {
+        loop {
+            match parse(self.ask(question)?.trim()) {
+                Some(value) => return Some(value),
+                None => println!("{}", retry),
+            }
+        }
+    }
+}
@@ -0,0 +1,349 @@
+// Worlds defined in data files.
+//
+// A world file sets the type of the hero, the obstacles and who can play it.
+// For example, in TOML:
+//
+//     name = "Knight World"
+//     hero = "Knight"
+//     power = 5
+//
+//     [eligibility]
+//     min_age = 12
+//
+//     [[obstacles]]
+//     name = "a dragon"
+//     action = "slays it"
+//     strength = 80
+//
+// JSON files have the same fields.
+
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use rand::RngCore;
+use serde::Deserialize;
+
+use crate::game::{roll_outcome, Outcome};
+use crate::{Eligibility, Hero, Obstacle, World, WorldRegistry};
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct WorldDefinition {
+    pub name: String,
+    /// Type of the hero such as "Frog".
+    pub hero: String,
+    /// Added to the rolls of the hero, from 0 to 100.
+    #[serde(default)]
+    pub power: u32,
+    /// Anyone can play the world if it's not set.
+    #[serde(default)]
+    pub eligibility: Eligibility,
+    pub obstacles: Vec<ObstacleDefinition>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ObstacleDefinition {
+    pub name: String,
+    pub action: String,
+    #[serde(default = "default_strength")]
+    pub strength: u32,
+}
+
+fn default_strength() -> u32 {
+    50
+}
+
+#[derive(Debug)]
+pub enum WorldFileError {
+    Io(io::Error),
+    Toml(toml::de::Error),
+    Json(serde_json::Error),
+    /// Only .toml and .json files are supported.
+    UnsupportedFormat(String),
+    /// The file is well-formed but the world cannot be played.
+    Invalid(String),
+}
+
+impl Error for WorldFileError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            WorldFileError::Io(e) => Some(e),
+            WorldFileError::Toml(e) => Some(e),
+            WorldFileError::Json(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for WorldFileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WorldFileError::Io(e) => write!(f, "Cannot read the file: {}", e),
+            WorldFileError::Toml(e) => write!(f, "Invalid TOML: {}", e),
+            WorldFileError::Json(e) => write!(f, "Invalid JSON: {}", e),
+            WorldFileError::UnsupportedFormat(filepath) => write!(f, "Not a world file: {}", filepath),
+            WorldFileError::Invalid(message) => write!(f, "Invalid world: {}", message),
+        }
+    }
+}
+
+impl From<io::Error> for WorldFileError {
+    fn from(e: io::Error) -> Self {
+        WorldFileError::Io(e)
+    }
+}
+
+impl From<toml::de::Error> for WorldFileError {
+    fn from(e: toml::de::Error) -> Self {
+        WorldFileError::Toml(e)
+    }
+}
+
+impl From<serde_json::Error> for WorldFileError {
+    fn from(e: serde_json::Error) -> Self {
+        WorldFileError::Json(e)
+    }
+}
+
+impl WorldDefinition {
+    /// Read a world from a .toml or .json file.
+    pub fn from_file(filepath: &Path) -> Result<WorldDefinition, WorldFileError> {
+        let extension = filepath.extension().and_then(|extension| extension.to_str()).unwrap_or("");
+        let definition: WorldDefinition = match extension {
+            "toml" => toml::from_str(&fs::read_to_string(filepath)?)?,
+            "json" => serde_json::from_str(&fs::read_to_string(filepath)?)?,
+            _ => return Err(WorldFileError::UnsupportedFormat(filepath.display().to_string())),
+        };
+
+        if definition.obstacles.is_empty() {
+            return Err(WorldFileError::Invalid(format!("{} has no obstacles", definition.name)));
+        }
+        if definition.power > 100 {
+            return Err(WorldFileError::Invalid(format!("power of {} must be from 0 to 100", definition.hero)));
+        }
+        if let Some(obstacle) = definition.obstacles.iter().find(|obstacle| !(1..=100).contains(&obstacle.strength)) {
+            return Err(WorldFileError::Invalid(format!("strength of {} must be from 1 to 100", obstacle.name)));
+        }
+        Ok(definition)
+    }
+}
+
+// Generic game
+struct DataHero {
+    name: String,
+    kind: String,
+    power: u32,
+}
+
+struct DataObstacle {
+    name: String,
+    action: String,
+    strength: u32,
+}
+
+struct DataWorld {
+    definition: WorldDefinition,
+    player_name: String,
+}
+
+impl Hero for DataHero {
+    fn interact_with(&self, obstacle: &dyn Obstacle, rng: &mut dyn RngCore) -> Outcome {
+        roll_outcome(rng, self.power, obstacle.strength())
+    }
+
+    fn describe(&self, obstacle: &dyn Obstacle, outcome: Outcome) -> String {
+        let act = match outcome {
+            Outcome::Win => obstacle.action().as_str(),
+            Outcome::Lose => "is hurt",
+            Outcome::Flee => "runs away",
+        };
+
+        format!("{} the {} encounters {} and {}!", self.name, self.kind, obstacle.name(), act)
+    }
+}
+
+impl Obstacle for DataObstacle {
+    fn name(&self) -> &String {
+        &self.name
+    }
+
+    fn action(&self) -> &String {
+        &self.action
+    }
+
+    fn strength(&self) -> u32 {
+        self.strength
+    }
+}
+
+impl World for DataWorld {
+    fn name(&self) -> &String {
+        &self.definition.name
+    }
+
+    fn player_name(&self) -> &String {
+        &self.player_name
+    }
+
+    fn make_character(&self) -> Box<dyn Hero> {
+        Box::new(DataHero {
+            name: self.player_name.clone(),
+            kind: self.definition.hero.clone(),
+            power: self.definition.power,
+        })
+    }
+
+    fn make_obstacles(&self) -> Vec<Box<dyn Obstacle>> {
+        self.definition.obstacles.iter()
+            .map(|obstacle| -> Box<dyn Obstacle> {
+                Box::new(DataObstacle {
+                    name: obstacle.name.clone(),
+                    action: obstacle.action.clone(),
+                    strength: obstacle.strength,
+                })
+            })
+            .collect()
+    }
+}
+
+impl WorldRegistry {
+    /// Register a world defined in data.
+    pub fn register_definition(&mut self, definition: WorldDefinition) -> Result<(), WorldFileError> {
+        let name = definition.name.clone();
+        let eligibility = definition.eligibility.clone();
+
+        self.register(&name, eligibility, move |player_name| {
+            Box::new(DataWorld {
+                definition: definition.clone(),
+                player_name,
+            })
+        }).map_err(WorldFileError::Invalid)
+    }
+
+    /// Register the worlds of all the .toml and .json files in a directory
+    /// in the order of their file names.
+    ///
+    /// A file which cannot be loaded is reported and skipped.
+    pub fn register_directory(&mut self, directory: &Path) -> Vec<(String, WorldFileError)> {
+        let mut errors = Vec::new();
+
+        let mut filepaths: Vec<_> = match fs::read_dir(directory) {
+            Ok(entries) => entries.filter_map(|entry| entry.ok().map(|entry| entry.path())).collect(),
+            Err(e) => {
+                errors.push((directory.display().to_string(), e.into()));
+                return errors;
+            },
+        };
+        filepaths.sort();
+
+        for filepath in filepaths {
+            let extension = filepath.extension().and_then(|extension| extension.to_str());
+            if !matches!(extension, Some("toml") | Some("json")) {
+                continue;
+            }
+            let registered = WorldDefinition::from_file(&filepath)
+                .and_then(|definition| self.register_definition(definition));
+            if let Err(e) = registered {
+                errors.push((filepath.display().to_string(), e));
+            }
+        }
+
+        errors
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::env;
+    use std::process;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Write a file with a name unique to the test run.
+    fn world_file(name: &str, data: &str) -> std::path::PathBuf {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+        let directory = env::temp_dir().join(format!(
+            "abstract_factory_{}_{}", process::id(), COUNT.fetch_add(1, Ordering::SeqCst)
+        ));
+        fs::create_dir_all(&directory).unwrap();
+        let filepath = directory.join(name);
+        fs::write(&filepath, data).unwrap();
+        filepath
+    }
+
+    fn invalid_message(result: Result<WorldDefinition, WorldFileError>) -> String {
+        match result {
+            Err(WorldFileError::Invalid(message)) => message,
+            Err(e) => panic!("Invalid error is expected: {}", e),
+            Ok(_) => panic!("Invalid error is expected"),
+        }
+    }
+
+    #[test]
+    fn load_bundled_worlds() {
+        let knight = WorldDefinition::from_file(Path::new("worlds/knight_world.toml")).unwrap();
+        assert_eq!(knight.name, "Knight World");
+        assert_eq!(knight.power, 5);
+        assert_eq!(knight.eligibility.min_age, Some(12));
+        assert_eq!(knight.obstacles.len(), 2);
+
+        let robot = WorldDefinition::from_file(Path::new("worlds/robot_world.json")).unwrap();
+        assert_eq!(robot.hero, "Robot");
+        assert_eq!(robot.power, 0);
+        assert_eq!((robot.eligibility.min_age, robot.eligibility.max_age), (Some(8), Some(14)));
+        assert_eq!(robot.obstacles[1].strength, 55);
+
+        let mut registry = WorldRegistry::new();
+        assert!(registry.register_directory(Path::new("worlds")).is_empty());
+        assert_eq!(registry.eligible_worlds(12), vec!["Knight World", "Robot World"]);
+        assert_eq!(registry.eligible_worlds(15), vec!["Knight World"]);
+    }
+
+    #[test]
+    fn missing_fields() {
+        let filepath = world_file("world.toml", "name = \"A\"\n[[obstacles]]\nname = \"a\"\naction = \"b\"\n");
+        match WorldDefinition::from_file(&filepath) {
+            Err(WorldFileError::Toml(e)) => assert!(e.to_string().contains("hero")),
+            _ => panic!("Toml error is expected"),
+        }
+
+        let filepath = world_file("world.json", r#"{"name": "A", "hero": "B", "obstacles": [{"name": "a"}]}"#);
+        match WorldDefinition::from_file(&filepath) {
+            Err(WorldFileError::Json(e)) => assert!(e.to_string().contains("action")),
+            _ => panic!("Json error is expected"),
+        }
+    }
+
+    #[test]
+    fn invalid_worlds() {
+        let filepath = world_file("world.json", r#"{"name": "A", "hero": "B", "obstacles": []}"#);
+        assert_eq!(invalid_message(WorldDefinition::from_file(&filepath)), "A has no obstacles");
+
+        let filepath = world_file("world.json", r#"{"name": "A", "hero": "B", "obstacles": [{"name": "a", "action": "b", "strength": 0}]}"#);
+        assert_eq!(invalid_message(WorldDefinition::from_file(&filepath)), "strength of a must be from 1 to 100");
+
+        let filepath = world_file("world.toml", "name = \"A\"\nhero = \"B\"\npower = 4294967295\n[[obstacles]]\nname = \"a\"\naction = \"b\"\n");
+        assert_eq!(invalid_message(WorldDefinition::from_file(&filepath)), "power of B must be from 0 to 100");
+
+        let filepath = world_file("world.txt", "");
+        assert!(matches!(WorldDefinition::from_file(&filepath), Err(WorldFileError::UnsupportedFormat(_))));
+    }
+
+    #[test]
+    fn bad_age_range() {
+        let filepath = world_file("world.json", r#"{
+            "name": "A", "hero": "B", "eligibility": {"min_age": 10, "max_age": 9},
+            "obstacles": [{"name": "a", "action": "b"}]
+        }"#);
+        let mut registry = WorldRegistry::new();
+        let errors = registry.register_directory(filepath.parent().unwrap());
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].1.to_string(), "Invalid world: A: min_age 10 is greater than max_age 9");
+        assert!(registry.make_world("A", String::from("P")).is_none());
+    }
+}
@@ -0,0 +1,414 @@
+// A game session in which the hero encounters the obstacles of a world one by one.
+
+use std::fmt;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::process;
+
+use rand::{Rng, RngCore, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{Hero, Obstacle, World};
+
+/// Hit points the hero starts with.
+pub const MAX_HP: u32 = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Outcome {
+    Win,
+    Lose,
+    Flee,
+}
+
+/// Decide an outcome by rolling 1 to 100 and adding the power of the hero.
+///
+/// The hero wins with a roll above the strength of the obstacle,
+/// flees with a roll above half of it, and loses otherwise.
+pub fn roll_outcome(rng: &mut dyn RngCore, power: u32, strength: u32) -> Outcome {
+    let roll = rng.gen_range(1..=100u32).saturating_add(power);
+
+    if roll > strength {
+        Outcome::Win
+    } else if roll > strength / 2 {
+        Outcome::Flee
+    } else {
+        Outcome::Lose
+    }
+}
+
+/// Result of a finished session.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Summary {
+    pub world_name: String,
+    pub score: u32,
+    pub hp: u32,
+    pub won: u32,
+    pub lost: u32,
+    pub fled: u32,
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let result = if self.hp == 0 { "Defeated" } else { "Cleared" };
+        writeln!(f, "{} {}!", result, self.world_name)?;
+        write!(f, "Score: {}, HP: {}/{} (won {}, lost {}, fled {})",
+               self.score, self.hp, MAX_HP, self.won, self.lost, self.fled)
+    }
+}
+
+/// A session saved in the middle, which is resumed with the same world.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedGame {
+    pub world_name: String,
+    pub player_name: String,
+    /// Seed the session was started with.
+    pub seed: u64,
+    /// Names of the obstacles to tell if the world has been changed since.
+    pub obstacles: Vec<String>,
+    pub position: usize,
+    pub score: u32,
+    pub hp: u32,
+    pub won: u32,
+    pub lost: u32,
+    pub fled: u32,
+    rng: ChaCha8Rng,
+}
+
+impl SavedGame {
+    /// Write to a temporary file next to `filepath` and rename it,
+    /// so that an old save is not lost if the game cannot be saved.
+    pub fn write(&self, filepath: &str) -> Result<(), String> {
+        // Serializing plain fields never fails.
+        let data = serde_json::to_string_pretty(self).unwrap();
+
+        let path = Path::new(filepath);
+        let filename = path.file_name().ok_or_else(|| format!("Cannot save to {}: Not a file path", filepath))?;
+        // The process ID keeps games saved at the same time from sharing the file.
+        let temp_filepath = path.with_file_name(format!(".{}.{}.tmp", filename.to_string_lossy(), process::id()));
+
+        let result = fs::File::create(&temp_filepath)
+            .and_then(|mut file| {
+                file.write_all(data.as_bytes())?;
+                file.sync_all()
+            })
+            .and_then(|_| fs::rename(&temp_filepath, path));
+        if let Err(e) = result {
+            let _ = fs::remove_file(&temp_filepath);
+            return Err(format!("Cannot save to {}: {}", filepath, e));
+        }
+
+        Ok(())
+    }
+
+    pub fn read(filepath: &str) -> Result<SavedGame, String> {
+        let data = fs::read_to_string(filepath).map_err(|e| format!("Cannot load {}: {}", filepath, e))?;
+        serde_json::from_str(&data).map_err(|e| format!("Invalid saved game in {}: {}", filepath, e))
+    }
+}
+
+pub struct GameEnvironment {
+    world_name: String,
+    player_name: String,
+    seed: u64,
+    hero: Box<dyn Hero>,
+    obstacles: Vec<Box<dyn Obstacle>>,
+    /// Index of the obstacle encountered next.
+    position: usize,
+    score: u32,
+    hp: u32,
+    won: u32,
+    lost: u32,
+    fled: u32,
+    rng: ChaCha8Rng,
+}
+
+impl GameEnvironment {
+    /// Start a session. The same seed gives the same outcomes.
+    pub fn new(world: &dyn World, seed: u64) -> GameEnvironment {
+        GameEnvironment {
+            world_name: world.name().clone(),
+            player_name: world.player_name().clone(),
+            seed,
+            hero: world.make_character(),
+            obstacles: world.make_obstacles(),
+            position: 0,
+            score: 0,
+            hp: MAX_HP,
+            won: 0,
+            lost: 0,
+            fled: 0,
+            rng: ChaCha8Rng::seed_from_u64(seed),
+        }
+    }
+
+    /// Resume a saved session. The world must be made by the same factory
+    /// as the one the session was saved with.
+    pub fn resume(world: &dyn World, saved: SavedGame) -> Result<GameEnvironment, String> {
+        let obstacles = world.make_obstacles();
+        let names: Vec<String> = obstacles.iter().map(|obstacle| obstacle.name().clone()).collect();
+
+        if *world.name() != saved.world_name || *world.player_name() != saved.player_name {
+            return Err(format!("The game was saved in {} by {}.", saved.world_name, saved.player_name));
+        }
+        if names != saved.obstacles || saved.position > names.len() {
+            return Err(format!("{} has been changed since the game was saved.", saved.world_name));
+        }
+
+        Ok(GameEnvironment {
+            world_name: saved.world_name,
+            player_name: saved.player_name,
+            seed: saved.seed,
+            hero: world.make_character(),
+            obstacles,
+            position: saved.position,
+            score: saved.score,
+            hp: saved.hp,
+            won: saved.won,
+            lost: saved.lost,
+            fled: saved.fled,
+            rng: saved.rng,
+        })
+    }
+
+    pub fn save(&self) -> SavedGame {
+        SavedGame {
+            world_name: self.world_name.clone(),
+            player_name: self.player_name.clone(),
+            seed: self.seed,
+            obstacles: self.obstacles.iter().map(|obstacle| obstacle.name().clone()).collect(),
+            position: self.position,
+            score: self.score,
+            hp: self.hp,
+            won: self.won,
+            lost: self.lost,
+            fled: self.fled,
+            rng: self.rng.clone(),
+        }
+    }
+
+    /// The seed the session was started with, which reproduces it from the start.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// The session is over when the hero has no HP or no obstacle is left.
+    pub fn is_over(&self) -> bool {
+        self.hp == 0 || self.position >= self.obstacles.len()
+    }
+
+    /// Encounter the next obstacle, and return the story of it.
+    pub fn play_turn(&mut self) -> Option<(Outcome, String)> {
+        if self.is_over() {
+            return None;
+        }
+
+        let obstacle = self.obstacles[self.position].as_ref();
+        let outcome = self.hero.interact_with(obstacle, &mut self.rng);
+        match outcome {
+            Outcome::Win => {
+                self.score += obstacle.strength();
+                self.won += 1;
+            },
+            Outcome::Lose => {
+                // A stronger obstacle hurts more.
+                let damage = obstacle.strength().div_ceil(10);
+                self.hp = self.hp.saturating_sub(damage);
+                self.lost += 1;
+            },
+            Outcome::Flee => self.fled += 1,
+        }
+        self.position += 1;
+
+        Some((outcome, self.hero.describe(obstacle, outcome)))
+    }
+
+    pub fn summary(&self) -> Summary {
+        Summary {
+            world_name: self.world_name.clone(),
+            score: self.score,
+            hp: self.hp,
+            won: self.won,
+            lost: self.lost,
+            fled: self.fled,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::env;
+    use std::process;
+
+    use crate::FrogWorld;
+
+    /// A hero who wins against strength 10, flees from 20 and loses to others.
+    struct TestHero;
+
+    struct TestObstacle {
+        name: String,
+        strength: u32,
+    }
+
+    struct TestWorld {
+        name: String,
+        player_name: String,
+        strengths: Vec<u32>,
+    }
+
+    impl Hero for TestHero {
+        fn interact_with(&self, obstacle: &dyn Obstacle, _rng: &mut dyn RngCore) -> Outcome {
+            match obstacle.strength() {
+                10 => Outcome::Win,
+                20 => Outcome::Flee,
+                _ => Outcome::Lose,
+            }
+        }
+
+        fn describe(&self, obstacle: &dyn Obstacle, outcome: Outcome) -> String {
+            format!("{:?} {}", outcome, obstacle.name())
+        }
+    }
+
+    impl Obstacle for TestObstacle {
+        fn name(&self) -> &String {
+            &self.name
+        }
+
+        fn action(&self) -> &String {
+            &self.name
+        }
+
+        fn strength(&self) -> u32 {
+            self.strength
+        }
+    }
+
+    impl World for TestWorld {
+        fn name(&self) -> &String {
+            &self.name
+        }
+
+        fn player_name(&self) -> &String {
+            &self.player_name
+        }
+
+        fn make_character(&self) -> Box<dyn Hero> {
+            Box::new(TestHero)
+        }
+
+        fn make_obstacles(&self) -> Vec<Box<dyn Obstacle>> {
+            self.strengths.iter()
+                .map(|&strength| -> Box<dyn Obstacle> {
+                    Box::new(TestObstacle { name: format!("obstacle {}", strength), strength })
+                })
+                .collect()
+        }
+    }
+
+    fn test_world(strengths: &[u32]) -> TestWorld {
+        TestWorld {
+            name: String::from("Test World"),
+            player_name: String::from("Tester"),
+            strengths: strengths.to_vec(),
+        }
+    }
+
+    fn frog_world() -> FrogWorld {
+        FrogWorld {
+            world_name: String::from("Frog World"),
+            player_name: String::from("Tester"),
+        }
+    }
+
+    fn play_all(environment: &mut GameEnvironment) -> Vec<Outcome> {
+        let mut outcomes = Vec::new();
+        while let Some((outcome, _)) = environment.play_turn() {
+            outcomes.push(outcome);
+        }
+        outcomes
+    }
+
+    #[test]
+    fn same_seed_same_outcomes() {
+        let roll = |seed: u64| -> Vec<Outcome> {
+            let mut rng = ChaCha8Rng::seed_from_u64(seed);
+            (0..50).map(|_| roll_outcome(&mut rng, 0, 50)).collect()
+        };
+
+        assert_eq!(roll(42), roll(42));
+        assert_ne!(roll(42), roll(43));
+
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        assert!((0..50).all(|_| roll_outcome(&mut rng, 100, 100) == Outcome::Win));
+        assert!((0..50).all(|_| roll_outcome(&mut rng, 0, 202) == Outcome::Lose));
+        assert!((0..50).all(|_| roll_outcome(&mut rng, u32::MAX, 100) == Outcome::Win));
+    }
+
+    #[test]
+    fn scoring() {
+        let mut environment = GameEnvironment::new(&test_world(&[10, 20, 30, 95, 10]), 0);
+
+        assert_eq!(play_all(&mut environment), vec![
+            Outcome::Win, Outcome::Flee, Outcome::Lose, Outcome::Lose, Outcome::Win,
+        ]);
+        assert!(environment.is_over());
+        assert_eq!(environment.summary(), Summary {
+            world_name: String::from("Test World"),
+            score: 20,
+            hp: 7,
+            won: 2,
+            lost: 2,
+            fled: 1,
+        });
+        assert_eq!(
+            environment.summary().to_string(),
+            "Cleared Test World!\nScore: 20, HP: 7/20 (won 2, lost 2, fled 1)"
+        );
+    }
+
+    #[test]
+    fn defeat_ends_session() {
+        let mut environment = GameEnvironment::new(&test_world(&[100, 100, 10]), 0);
+
+        assert_eq!(play_all(&mut environment), vec![Outcome::Lose, Outcome::Lose]);
+        assert_eq!(environment.summary().hp, 0);
+        assert!(environment.summary().to_string().starts_with("Defeated Test World!"));
+    }
+
+    #[test]
+    fn saved_game_round_trip() {
+        let world = frog_world();
+        let mut original = GameEnvironment::new(&world, 7);
+        original.play_turn();
+
+        let filepath = env::temp_dir().join(format!("abstract_factory_{}_savegame.json", process::id()));
+        let filepath = filepath.to_str().unwrap();
+        original.save().write(filepath).unwrap();
+        let saved = SavedGame::read(filepath).unwrap();
+        fs::remove_file(filepath).unwrap();
+
+        assert_eq!(saved.position, 1);
+        assert_eq!(saved.seed, 7);
+        let mut resumed = GameEnvironment::resume(&world, saved).unwrap();
+        assert_eq!(play_all(&mut resumed), play_all(&mut original));
+        assert_eq!(resumed.summary(), original.summary());
+    }
+
+    #[test]
+    fn resume_in_other_world() {
+        let saved = GameEnvironment::new(&frog_world(), 7).save();
+
+        let other_player = FrogWorld { player_name: String::from("Other"), ..frog_world() };
+        assert!(GameEnvironment::resume(&other_player, saved.clone()).is_err());
+
+        let changed = TestWorld { name: String::from("Frog World"), ..test_world(&[10]) };
+        assert_eq!(
+            GameEnvironment::resume(&changed, saved).err(),
+            Some(String::from("Frog World has been changed since the game was saved."))
+        );
+    }
+}
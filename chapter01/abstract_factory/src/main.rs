@@ -1,20 +1,41 @@
-use std::io;
-use std::io::Write;
+use std::env;
+use std::path::Path;
+use std::process;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use rand::RngCore;
+use serde::Deserialize;
+
+mod cli;
+mod data_world;
+mod game;
+
+use cli::{Options, Prompter, USAGE};
+use game::{roll_outcome, GameEnvironment, Outcome, SavedGame};
 
 
 // Traits
 trait Hero {
-    fn interact_with(&self, obstacle: &Box<dyn Obstacle>);
+    /// Decide what happens when the hero encounters the obstacle.
+    fn interact_with(&self, obstacle: &dyn Obstacle, rng: &mut dyn RngCore) -> Outcome;
+    /// Tell the story of an encounter.
+    fn describe(&self, obstacle: &dyn Obstacle, outcome: Outcome) -> String;
 }
 
 trait Obstacle {
     fn name(&self) -> &String;
+    /// What the hero does to the obstacle when the hero wins.
     fn action(&self) -> &String;
+    /// Points to win, from 1 to 100.
+    fn strength(&self) -> u32;
 }
 
 trait World {
+    fn name(&self) -> &String;
+    fn player_name(&self) -> &String;
     fn make_character(&self) -> Box<dyn Hero>;
-    fn make_obstacle(&self) -> Box<dyn Obstacle>;
+    /// Make the obstacles in the order the hero encounters them.
+    fn make_obstacles(&self) -> Vec<Box<dyn Obstacle>>;
 }
 
 // Frog game
@@ -25,6 +46,7 @@ struct Frog {
 struct Bug {
     name: String,
     action: String,
+    strength: u32,
 }
 
 struct FrogWorld {
@@ -38,14 +60,19 @@ impl Hero for Frog {
     //     Box::new(Frog { player_name: player_name })
     // }
 
-    fn interact_with(&self, obstacle: &Box<dyn Obstacle>) {
+    fn interact_with(&self, obstacle: &dyn Obstacle, rng: &mut dyn RngCore) -> Outcome {
+        roll_outcome(rng, 0, obstacle.strength())
+    }
+
+    fn describe(&self, obstacle: &dyn Obstacle, outcome: Outcome) -> String {
         let obs = obstacle.name();
-        let act = obstacle.action();
+        let act = match outcome {
+            Outcome::Win => obstacle.action().as_str(),
+            Outcome::Lose => "gets stung",
+            Outcome::Flee => "hops away",
+        };
 
-        println!("{} the Frog encounters {} and {}!",
-                 self.name,
-                 obs,
-                 act);
+        format!("{} the Frog encounters {} and {}!", self.name, obs, act)
     }
 }
 
@@ -57,19 +84,38 @@ impl Obstacle for Bug {
     fn action(&self) -> &String {
         &self.action
     }
+
+    fn strength(&self) -> u32 {
+        self.strength
+    }
 }
 
 impl World for FrogWorld {
+    fn name(&self) -> &String {
+        &self.world_name
+    }
+
+    fn player_name(&self) -> &String {
+        &self.player_name
+    }
+
     fn make_character(&self) -> Box<dyn Hero> {
         let player_name = self.player_name.clone();
         Box::new(Frog { name: player_name })
     }
 
-    fn make_obstacle(&self) -> Box<dyn Obstacle> {
-        Box::new(Bug {
-            name: String::from("a bug"),
-            action: String::from("eats it"),
-        })
+    fn make_obstacles(&self) -> Vec<Box<dyn Obstacle>> {
+        let bugs = [("a bug", "eats it", 20), ("a dragonfly", "catches it", 40), ("a hornet", "swallows it", 60)];
+
+        bugs.iter()
+            .map(|&(name, action, strength)| -> Box<dyn Obstacle> {
+                Box::new(Bug {
+                    name: String::from(name),
+                    action: String::from(action),
+                    strength,
+                })
+            })
+            .collect()
     }
 }
 
@@ -81,6 +127,7 @@ struct Wizard {
 struct Ork {
     name: String,
     action: String,
+    strength: u32,
 }
 
 struct WizardWorld {
@@ -89,14 +136,20 @@ struct WizardWorld {
 }
 
 impl Hero for Wizard {
-    fn interact_with(&self, obstacle: &Box<dyn Obstacle>) {
+    fn interact_with(&self, obstacle: &dyn Obstacle, rng: &mut dyn RngCore) -> Outcome {
+        // Magic helps a little.
+        roll_outcome(rng, 10, obstacle.strength())
+    }
+
+    fn describe(&self, obstacle: &dyn Obstacle, outcome: Outcome) -> String {
         let obs = obstacle.name();
-        let act = obstacle.action();
+        let act = match outcome {
+            Outcome::Win => obstacle.action().as_str(),
+            Outcome::Lose => "is wounded",
+            Outcome::Flee => "teleports away",
+        };
 
-        println!("{} the Wizard encounters {} and {}!",
-                 self.name,
-                 obs,
-                 act);
+        format!("{} the Wizard encounters {} and {}!", self.name, obs, act)
     }
 }
 
@@ -108,77 +161,385 @@ impl Obstacle for Ork {
     fn action(&self) -> &String {
         &self.action
     }
+
+    fn strength(&self) -> u32 {
+        self.strength
+    }
 }
 
 impl World for WizardWorld {
+    fn name(&self) -> &String {
+        &self.world_name
+    }
+
+    fn player_name(&self) -> &String {
+        &self.player_name
+    }
+
     fn make_character(&self) -> Box<dyn Hero> {
         let player_name = self.player_name.clone();
         Box::new(Wizard { name: player_name })
     }
 
-    fn make_obstacle(&self) -> Box<dyn Obstacle> {
-        Box::new(Ork {
-            name: String::from("an evil ork"),
-            action: String::from("kills it"),
-        })
+    fn make_obstacles(&self) -> Vec<Box<dyn Obstacle>> {
+        let orks = [("an evil ork", "kills it", 50), ("an ork shaman", "breaks its staff", 65), ("an ork warlord", "banishes it", 85)];
+
+        orks.iter()
+            .map(|&(name, action, strength)| -> Box<dyn Obstacle> {
+                Box::new(Ork {
+                    name: String::from(name),
+                    action: String::from(action),
+                    strength,
+                })
+            })
+            .collect()
     }
 }
 
-struct GameEnvironment {
-    hero: Box<dyn Hero>,
-    obstacle: Box<dyn Obstacle>,
+// Registry
+/// Ages which can play a world. Both ends of `min_age` and `max_age` are inclusive.
+#[derive(Debug, Clone, Default, Deserialize)]
+struct Eligibility {
+    min_age: Option<u32>,
+    max_age: Option<u32>,
+    /// Exclusive upper bound set by `under`, which is not in world files.
+    #[serde(skip)]
+    under: Option<u32>,
 }
 
-impl GameEnvironment {
-    fn play(&self) {
-        self.hero.interact_with(&self.obstacle);
+impl Eligibility {
+    fn under(age: u32) -> Eligibility {
+        Eligibility { under: Some(age), ..Eligibility::default() }
+    }
+
+    fn at_least(age: u32) -> Eligibility {
+        Eligibility { min_age: Some(age), ..Eligibility::default() }
+    }
+
+    /// Check that the range has any ages.
+    fn validate(&self) -> Result<(), String> {
+        match (self.min_age, self.max_age, self.under) {
+            (Some(min_age), Some(max_age), _) if min_age > max_age => {
+                Err(format!("min_age {} is greater than max_age {}", min_age, max_age))
+            },
+            (min_age, _, Some(under)) if min_age.unwrap_or(0) >= under => {
+                Err(format!("no age from {} is under {}", min_age.unwrap_or(0), under))
+            },
+            _ => Ok(()),
+        }
+    }
+
+    fn allows(&self, age: u32) -> bool {
+        self.min_age.is_none_or(|min_age| min_age <= age)
+            && self.max_age.is_none_or(|max_age| age <= max_age)
+            && self.under.is_none_or(|under| age < under)
+    }
+}
+
+struct RegisteredWorld {
+    name: String,
+    eligibility: Eligibility,
+    factory: Box<dyn Fn(String) -> Box<dyn World>>,
+}
+
+/// Worlds which can be played, in the order they are registered.
+struct WorldRegistry {
+    worlds: Vec<RegisteredWorld>,
+}
+
+impl WorldRegistry {
+    fn new() -> WorldRegistry {
+        WorldRegistry { worlds: Vec::new() }
+    }
+
+    /// Register a world with a factory which makes it for a player.
+    /// A world registered with the same name is replaced.
+    /// A world which no one can play is not registered.
+    fn register<F>(&mut self, name: &str, eligibility: Eligibility, factory: F) -> Result<(), String>
+    where
+        F: Fn(String) -> Box<dyn World> + 'static,
+    {
+        eligibility.validate().map_err(|e| format!("{}: {}", name, e))?;
+
+        self.worlds.retain(|world| world.name != name);
+        self.worlds.push(RegisteredWorld {
+            name: String::from(name),
+            eligibility,
+            factory: Box::new(factory),
+        });
+        Ok(())
+    }
+
+    fn eligible_worlds(&self, age: u32) -> Vec<&String> {
+        self.worlds.iter()
+            .filter(|world| world.eligibility.allows(age))
+            .map(|world| &world.name)
+            .collect()
+    }
+
+    fn make_world(&self, name: &str, player_name: String) -> Option<Box<dyn World>> {
+        self.worlds.iter()
+            .find(|world| world.name == name)
+            .map(|world| (world.factory)(player_name))
     }
 }
 
-fn generate_world(name: String, age: u32) -> Box<dyn World> {
-    if age < 18 {
-        let world_name = String::from("Frog World");
-        println!("\n\n\t------ {} ------", &world_name);
-        
-        Box::new(FrogWorld { 
-            world_name: world_name,
-            player_name: name,
+fn default_registry() -> WorldRegistry {
+    let mut registry = WorldRegistry::new();
+
+    let frog_world = registry.register("Frog World", Eligibility::under(18), |player_name| {
+        Box::new(FrogWorld {
+            world_name: String::from("Frog World"),
+            player_name,
         })
-    } else {
-        let world_name = String::from("Wizard World");
-        println!("\n\n\t------ {} ------", &world_name);
-        
-        Box::new(WizardWorld { 
-            world_name: world_name,
-            player_name: name,
+    });
+    let wizard_world = registry.register("Wizard World", Eligibility::at_least(18), |player_name| {
+        Box::new(WizardWorld {
+            world_name: String::from("Wizard World"),
+            player_name,
         })
-    }
+    });
+    frog_world.and(wizard_world).expect("built-in worlds can be played");
+
+    registry
+}
+
+fn generate_world(registry: &WorldRegistry, prompter: &mut Prompter, name: String, age: u32) -> Option<Box<dyn World>> {
+    let worlds = registry.eligible_worlds(age);
+
+    // The player chooses only when more than one world is eligible.
+    let world_name = match worlds.len() {
+        0 => return None,
+        1 => worlds[0].clone(),
+        _ => {
+            println!("Choose a world:");
+            for (i, world_name) in worlds.iter().enumerate() {
+                println!("  {}. {}", i + 1, world_name);
+            }
+            let i = prompter.ask_until("> ", "Please type the number of a world!", |answer| {
+                answer.parse::<usize>().ok().filter(|&i| 1 <= i && i <= worlds.len())
+            })?;
+            worlds[i - 1].clone()
+        },
+    };
+
+    let world = registry.make_world(&world_name, name)?;
+    println!("\n\n\t------ {} ------", world.name());
+
+    Some(world)
 }
 
 fn main() {
-    let mut name = String::new();
-    let mut age = String::new();
-
-    print!("Hello. What's your name? ");
-    // https://stackoverflow.com/questions/37531903/how-do-i-print-output-without-a-trailing-newline-in-rust
-    io::stdout().flush().unwrap();
-    io::stdin().read_line(&mut name)
-        .expect("Failed to read line");
-    let name = String::from(name.trim_end_matches("\n"));
-
-    print!("Welcome {}. How old are you? ", &name);
-    io::stdout().flush().unwrap();
-    io::stdin().read_line(&mut age)
-        .expect("Failed to read line");
-    let age: u32 = age.trim().parse()
-        .expect("Please type a number!");
-
-    let world = generate_world(name, age);
-
-    let environment = GameEnvironment {
-        hero: world.make_character(),
-	obstacle: world.make_obstacle(),
+    let args: Vec<String> = env::args().skip(1).collect();
+    let options = match Options::parse(&args) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}\n{}", e, USAGE);
+            process::exit(2);
+        },
+    };
+    let mut prompter = match &options.script {
+        Some(script) => Prompter::script(script).unwrap_or_else(|e| {
+            eprintln!("Cannot read {}: {}", script, e);
+            process::exit(2);
+        }),
+        None => Prompter::stdin(),
+    };
+
+    let mut registry = default_registry();
+    // Worlds in files are added to the built-in ones.
+    let worlds_directory = Path::new("worlds");
+    if worlds_directory.is_dir() {
+        for (filepath, e) in registry.register_directory(worlds_directory) {
+            eprintln!("Skipped {}: {}", filepath, e);
+        }
+    }
+
+    if let Err(e) = play(&registry, &mut prompter, &options) {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+}
+
+/// File a game is saved to when `save` is typed without a file.
+const DEFAULT_SAVE_FILE: &str = "savegame.json";
+
+fn play(registry: &WorldRegistry, prompter: &mut Prompter, options: &Options) -> Result<(), String> {
+    let mut environment = match &options.load {
+        Some(filepath) => resume(registry, filepath)?,
+        None => match start(registry, prompter, options)? {
+            Some(environment) => environment,
+            None => return Ok(()),
+        },
+    };
+
+    if !play_turns(&mut environment, prompter)? {
+        return Ok(());
+    }
+
+    println!("\n{}", environment.summary());
+    // A session is reproduced with the seed.
+    println!("Seed: {}", environment.seed());
+
+    Ok(())
+}
+
+/// Start a new session. `None` means there is no world to play.
+fn start(registry: &WorldRegistry, prompter: &mut Prompter, options: &Options) -> Result<Option<GameEnvironment>, String> {
+    let no_answer = || String::from("No answer is left.");
+
+    let name = match &options.name {
+        Some(name) => name.clone(),
+        None => prompter.ask("Hello. What's your name? ").ok_or_else(no_answer)?,
+    };
+
+    let age = match options.age {
+        Some(age) => age,
+        None => prompter.ask_until(
+            &format!("Welcome {}. How old are you? ", &name),
+            "Please type a number!",
+            |answer| answer.parse().ok(),
+        ).ok_or_else(no_answer)?,
+    };
+
+    let world = match &options.world {
+        Some(world_name) => {
+            if !registry.eligible_worlds(age).contains(&world_name) {
+                return Err(format!("{} cannot be played at the age of {}.", world_name, age));
+            }
+            let world = registry.make_world(world_name, name)
+                .ok_or_else(|| format!("Unknown world: {}", world_name))?;
+            println!("\n\n\t------ {} ------", world.name());
+            world
+        },
+        None => match generate_world(registry, prompter, name, age) {
+            Some(world) => world,
+            None if registry.eligible_worlds(age).is_empty() => {
+                println!("Sorry, there is no world for you.");
+                return Ok(None);
+            },
+            None => return Err(no_answer()),
+        },
     };
 
-    environment.play();
+    let seed = options.seed.unwrap_or_else(|| {
+        SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_secs())
+    });
+
+    Ok(Some(GameEnvironment::new(world.as_ref(), seed)))
+}
+
+/// Resume a saved session in the world made by the same factory.
+fn resume(registry: &WorldRegistry, filepath: &str) -> Result<GameEnvironment, String> {
+    let saved = SavedGame::read(filepath)?;
+    let world = registry.make_world(&saved.world_name, saved.player_name.clone())
+        .ok_or_else(|| format!("Unknown world: {}", saved.world_name))?;
+
+    println!("Welcome back {}.", world.player_name());
+    println!("\n\n\t------ {} ------", world.name());
+
+    GameEnvironment::resume(world.as_ref(), saved)
+}
+
+/// Play until the session is over or the player stops.
+/// The player is asked after each turn only when the prompter is interactive.
+/// Return whether the session is over.
+fn play_turns(environment: &mut GameEnvironment, prompter: &mut Prompter) -> Result<bool, String> {
+    while let Some((_, story)) = environment.play_turn() {
+        println!("{}", story);
+        if environment.is_over() || !prompter.is_interactive() {
+            continue;
+        }
+
+        // Without answers, the session just goes on.
+        while let Some(command) = prompter.ask("What next? [Enter] continue, save [FILE], quit: ") {
+            let mut words = command.split_whitespace();
+
+            match (words.next(), words.next()) {
+                (None, _) => break,
+                (Some("save"), filepath) => {
+                    let filepath = filepath.unwrap_or(DEFAULT_SAVE_FILE);
+                    environment.save().write(filepath)?;
+                    println!("Saved to {}. Resume with --load {}.", filepath, filepath);
+                    return Ok(false);
+                },
+                (Some("quit"), _) => return Ok(false),
+                _ => println!("Please type save, quit or nothing!"),
+            }
+        }
+    }
+
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frog_world(player_name: String) -> Box<dyn World> {
+        Box::new(FrogWorld {
+            world_name: String::from("Frog World"),
+            player_name,
+        })
+    }
+
+    #[test]
+    fn eligibility_boundaries() {
+        let under = Eligibility::under(18);
+        assert!(under.allows(0));
+        assert!(under.allows(17));
+        assert!(!under.allows(18));
+        assert!(!Eligibility::under(0).allows(0));
+
+        let at_least = Eligibility::at_least(18);
+        assert!(!at_least.allows(17));
+        assert!(at_least.allows(18));
+        assert!(at_least.allows(u32::MAX));
+
+        let range = Eligibility { min_age: Some(8), max_age: Some(14), ..Eligibility::default() };
+        assert!(!range.allows(7));
+        assert!(range.allows(8));
+        assert!(range.allows(14));
+        assert!(!range.allows(15));
+
+        let single = Eligibility { min_age: Some(10), max_age: Some(10), ..Eligibility::default() };
+        assert_eq!(single.validate(), Ok(()));
+        assert!(single.allows(10));
+        assert!(Eligibility::default().allows(99));
+    }
+
+    #[test]
+    fn default_worlds() {
+        let registry = default_registry();
+
+        assert_eq!(registry.eligible_worlds(17), vec!["Frog World"]);
+        assert_eq!(registry.eligible_worlds(18), vec!["Wizard World"]);
+        assert!(registry.make_world("Moon World", String::from("A")).is_none());
+    }
+
+    #[test]
+    fn replace_registered_world() {
+        let mut registry = default_registry();
+        registry.register("Frog World", Eligibility::at_least(30), frog_world).unwrap();
+
+        assert_eq!(registry.eligible_worlds(10), Vec::<&String>::new());
+        assert_eq!(registry.eligible_worlds(30), vec!["Wizard World", "Frog World"]);
+        assert_eq!(registry.make_world("Frog World", String::from("A")).unwrap().player_name(), "A");
+    }
+
+    #[test]
+    fn reject_empty_age_range() {
+        let mut registry = WorldRegistry::new();
+        let eligibility = Eligibility { min_age: Some(15), max_age: Some(14), ..Eligibility::default() };
+
+        assert_eq!(
+            registry.register("Frog World", eligibility, frog_world),
+            Err(String::from("Frog World: min_age 15 is greater than max_age 14"))
+        );
+        assert_eq!(
+            registry.register("Frog World", Eligibility::under(0), frog_world),
+            Err(String::from("Frog World: no age from 0 is under 0"))
+        );
+        assert!(registry.make_world("Frog World", String::from("A")).is_none());
+    }
 }
@@ -0,0 +1,147 @@
+// Play the game through the binary as a player would.
+
+use std::env;
+use std::fs;
+use std::io::Read;
+use std::path::PathBuf;
+use std::process::{self, Command, Output, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+fn run(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_abstract_factory"))
+        .args(args)
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .output()
+        .unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+/// Return a path in the temp directory unique to the test run.
+fn temp_path(name: &str) -> PathBuf {
+    static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+    env::temp_dir().join(format!(
+        "abstract_factory_{}_{}_{}", process::id(), COUNT.fetch_add(1, Ordering::SeqCst), name
+    ))
+}
+
+fn script(name: &str, answers: &str) -> String {
+    let filepath = temp_path(name);
+    fs::write(&filepath, answers).unwrap();
+    filepath.to_str().unwrap().to_string()
+}
+
+#[test]
+fn same_seed_same_session() {
+    let args = ["--name", "Al", "--age", "40", "--world", "Wizard World", "--seed", "7"];
+    let first = run(&args);
+    let second = run(&args);
+
+    assert!(first.status.success());
+    assert_eq!(stdout(&first), stdout(&second));
+    assert!(stdout(&first).contains("------ Wizard World ------"));
+    assert!(stdout(&first).contains("Al the Wizard encounters an evil ork and"));
+    assert!(stdout(&first).ends_with("Seed: 7\n"));
+}
+
+#[test]
+fn scripted_answers() {
+    let filepath = script("scripted.txt", "Bob\nabc\n12\n1\n");
+    let output = run(&["--script", &filepath, "--seed", "1"]);
+    let stdout = stdout(&output);
+
+    assert!(output.status.success());
+    assert!(stdout.contains("Hello. What's your name? Bob\n"));
+    // An invalid age is asked again instead of a panic.
+    assert!(stdout.contains("How old are you? abc\nPlease type a number!\n"));
+    assert!(stdout.contains("How old are you? 12\n"));
+    assert!(stdout.contains("------ Frog World ------"));
+    assert!(stdout.contains("Bob the Frog encounters a bug and"));
+    fs::remove_file(&filepath).unwrap();
+}
+
+#[test]
+fn script_without_enough_answers() {
+    let filepath = script("short.txt", "Bob\n");
+    let output = run(&["--script", &filepath]);
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("No answer is left."));
+    fs::remove_file(&filepath).unwrap();
+}
+
+#[test]
+fn invalid_options() {
+    assert_eq!(run(&["--age", "x"]).status.code(), Some(2));
+    assert_eq!(run(&["--speed", "1"]).status.code(), Some(2));
+
+    let output = run(&["--name", "Bob", "--age", "10", "--world", "Wizard World"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("cannot be played at the age of 10"));
+}
+
+#[test]
+fn save_and_resume() {
+    let save_file = temp_path("savegame.json");
+    let save_file = save_file.to_str().unwrap();
+    let filepath = script("save.txt", &format!("save {}\n", save_file));
+    let args = ["--name", "Al", "--age", "40", "--world", "Wizard World", "--seed", "9"];
+
+    let saved = stdout(&run(&[&args[..], &["--script", &filepath]].concat()));
+    assert!(saved.contains(&format!("Saved to {}.", save_file)));
+    assert!(!saved.contains("Score:"));
+
+    let resumed = run(&["--load", save_file]);
+    assert!(resumed.status.success());
+    let resumed = stdout(&resumed);
+    assert!(resumed.starts_with("Welcome back Al.\n"));
+    assert!(resumed.ends_with("Seed: 9\n"));
+
+    // The resumed session goes on as if it had not been stopped.
+    let uninterrupted = stdout(&run(&args));
+    let stories = |output: &str| -> Vec<String> {
+        output.lines()
+            .filter(|line| line.contains(" encounters ") || line.starts_with("Score:"))
+            .map(|line| line.rsplit(": ").next().unwrap().to_string())
+            .collect()
+    };
+    assert_eq!([stories(&saved), stories(&resumed)].concat(), stories(&uninterrupted));
+    fs::remove_file(&filepath).unwrap();
+    fs::remove_file(save_file).unwrap();
+}
+
+#[test]
+fn open_stdin_without_answers() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_abstract_factory"))
+        .args(["--name", "Al", "--age", "40", "--world", "Wizard World", "--seed", "7"])
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // Nothing is written to stdin, and it stays open until the game ends.
+    let _stdin = child.stdin.take();
+
+    let deadline = Instant::now() + Duration::from_secs(10);
+    let status = loop {
+        if let Some(status) = child.try_wait().unwrap() {
+            break status;
+        }
+        if Instant::now() > deadline {
+            child.kill().unwrap();
+            panic!("The game waits for stdin.");
+        }
+        thread::sleep(Duration::from_millis(20));
+    };
+
+    let mut output = String::new();
+    child.stdout.take().unwrap().read_to_string(&mut output).unwrap();
+    assert!(status.success());
+    assert!(!output.contains("What next?"));
+    assert!(output.ends_with("Seed: 7\n"));
+}
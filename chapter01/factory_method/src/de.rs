@@ -0,0 +1,513 @@
+//! Deserialization from extracted data into user types with serde.
+//!
+//! Nodes are read in the same way as JSON made by `Document::to_json`,
+//! with some leniency for data which came from untyped formats:
+//!
+//! * A scalar can be read as a string, so `<postalCode>10021</postalCode>` fits a `String`.
+//! * A node which is not a sequence can be read as a sequence of one element,
+//!   since XML cannot tell them apart.
+//! * The root element is skipped when the document is read as a sequence,
+//!   so the root of person.xml, `<persons><person/><person/></persons>`, fits a `Vec<Person>`.
+//!   Other wrapper elements like `<phoneNumbers>` are only skipped for fields
+//!   which are marked with `#[serde(deserialize_with = "factory_method::from_wrapper")]`.
+//! * Attributes are entries with the `@` prefix,
+//!   and the text of a scalar with attributes is the `#text` entry.
+
+use std::borrow::Cow;
+use std::fmt;
+use std::marker::PhantomData;
+
+use serde::de::{self, Deserialize, DeserializeOwned, IntoDeserializer, Visitor};
+
+use crate::document::{Document, Node, NodeKind, Position};
+use crate::DataExtractionError;
+
+impl Document {
+    /// Deserialize the whole document into a user type.
+    pub fn deserialize<T: DeserializeOwned>(&self) -> Result<T, DataExtractionError> {
+        T::deserialize(NodeDeserializer::root(Cow::Borrowed(self.root())))
+            .map_err(|e| DataExtractionError::Deserialize {
+                message: e.message,
+                path: e.path.unwrap_or_else(|| String::from("$")),
+                line: e.position.map(|position| position.line),
+                column: e.position.map(|position| position.column),
+            })
+    }
+}
+
+/// Error while deserializing.
+///
+/// An error made by serde has no location,
+/// so the node being deserialized gives its path and position to it.
+#[derive(Debug)]
+pub(crate) struct Error {
+    message: String,
+    path: Option<String>,
+    position: Option<Position>,
+}
+
+impl std::error::Error for Error {}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Error {
+        Error {
+            message: msg.to_string(),
+            path: None,
+            position: None,
+        }
+    }
+}
+
+impl Error {
+    fn locate(mut self, path: &str, position: Option<Position>) -> Error {
+        if self.path.is_none() {
+            self.path = Some(path.to_string());
+            self.position = position;
+        }
+        self
+    }
+}
+
+/// Read a wrapper element like `<phoneNumbers>` as its children.
+///
+/// Use it for fields with `#[serde(deserialize_with = "factory_method::from_wrapper")]`.
+/// An empty wrapper is read as the default value, such as an empty `Vec`.
+pub fn from_wrapper<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: de::Deserializer<'de>,
+    T: Deserialize<'de> + Default,
+{
+    deserializer.deserialize_any(WrapperVisitor(PhantomData))
+}
+
+struct WrapperVisitor<T>(PhantomData<T>);
+
+impl<'de, T: Deserialize<'de> + Default> Visitor<'de> for WrapperVisitor<T> {
+    type Value = T;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "a wrapper element with child elements of one name")
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<T, E> {
+        Ok(T::default())
+    }
+
+    fn visit_map<A: de::MapAccess<'de>>(self, mut map: A) -> Result<T, A::Error> {
+        if map.next_key::<de::IgnoredAny>()?.is_none() {
+            return Ok(T::default());
+        }
+        let value = map.next_value()?;
+
+        match map.next_key::<String>()? {
+            Some(key) => Err(de::Error::custom(format!("unexpected `{}` in a wrapper element", key))),
+            None => Ok(value),
+        }
+    }
+}
+
+/// Wrapper elements which can be skipped at the root, the document and the root element.
+const ROOT_WRAPPERS: usize = 2;
+
+#[derive(Clone)]
+pub(crate) struct NodeDeserializer<'a> {
+    node: Cow<'a, Node>,
+    /// Path to the node in the query syntax.
+    path: String,
+    /// How many wrapper elements can be skipped to read the node as a sequence.
+    wrappers: usize,
+}
+
+impl<'a> NodeDeserializer<'a> {
+    pub(crate) fn new(node: Cow<'a, Node>, path: String) -> NodeDeserializer<'a> {
+        NodeDeserializer { node, path, wrappers: 0 }
+    }
+
+    /// Deserializer for the root of a document, whose root element is skipped for a sequence.
+    pub(crate) fn root(node: Cow<'a, Node>) -> NodeDeserializer<'a> {
+        NodeDeserializer { node, path: String::from("$"), wrappers: ROOT_WRAPPERS }
+    }
+
+    /// Allow a wrapper element to be skipped, where a caller expects one.
+    pub(crate) fn wrapper(mut self) -> NodeDeserializer<'a> {
+        self.wrappers = self.wrappers.max(1);
+        self
+    }
+
+    pub(crate) fn path(&self) -> &str {
+        &self.path
+    }
+
+    fn locate<T>(path: &str, position: Option<Position>, result: Result<T, Error>) -> Result<T, Error> {
+        result.map_err(|e| e.locate(path, position))
+    }
+
+    /// Entries of a map including attributes.
+    pub(crate) fn entries(&self) -> Option<Vec<(String, Cow<'a, Node>)>> {
+        let node: &Node = &self.node;
+        let mut entries: Vec<(String, Cow<'a, Node>)> = node.attributes()
+            .iter()
+            .map(|(name, value)| (format!("@{}", name), Cow::Owned(attribute_node(value, node))))
+            .collect();
+
+        match (node.kind(), &self.node) {
+            (NodeKind::Map(_), Cow::Borrowed(borrowed)) => {
+                let map = borrowed.as_map().unwrap();
+                entries.extend(map.iter().map(|(key, child)| (key.clone(), Cow::Borrowed(child))));
+            },
+            (NodeKind::Map(map), Cow::Owned(_)) => {
+                entries.extend(map.iter().map(|(key, child)| (key.clone(), Cow::Owned(child.clone()))));
+            },
+            (NodeKind::Sequence(_), _) | (NodeKind::Null, _) => return None,
+            // Only a scalar with attributes can be a map.
+            _ if entries.is_empty() => return None,
+            _ => {
+                // Text keeps being untyped, so it's still read leniently.
+                let mut text = if node.is_untyped() {
+                    Node::from_text(&node.to_string())
+                } else {
+                    Node::new(node.kind().clone())
+                };
+                text.set_position(node.position());
+                entries.push((String::from("#text"), Cow::Owned(text)));
+            },
+        }
+
+        Some(entries)
+    }
+
+    /// The single entry of a wrapper element like `<persons>`, whose children are the elements.
+    fn wrapped(&self) -> Option<(String, Cow<'a, Node>)> {
+        if self.wrappers == 0 || self.node.as_sequence().is_some() {
+            return None;
+        }
+
+        let entries = self.entries()?;
+        let wrapped = entries.len() == 1 && {
+            let entry = &entries[0].1;
+            entry.as_map().is_some() || entry.as_sequence().is_some() || !entry.attributes().is_empty()
+        };
+        if wrapped {
+            entries.into_iter().next()
+        } else {
+            None
+        }
+    }
+
+    /// Skip the wrapper elements which can be skipped, keeping the path to the node.
+    pub(crate) fn unwrap(self) -> NodeDeserializer<'a> {
+        match self.wrapped() {
+            Some((key, entry)) => NodeDeserializer {
+                node: entry,
+                path: format!("{}.{}", self.path, key),
+                wrappers: self.wrappers - 1,
+            }.unwrap(),
+            None => self,
+        }
+    }
+
+    /// Whether the node is a sequence or a wrapper element around one which can be skipped.
+    pub(crate) fn is_sequence(&self) -> bool {
+        self.node.as_sequence().is_some() || self.wrapped().is_some()
+    }
+
+    /// Elements of a sequence with the leniency described in the module.
+    ///
+    /// Wrapper elements are not skipped, so call `unwrap` first for them.
+    pub(crate) fn elements(&self) -> Vec<Cow<'a, Node>> {
+        match (&self.node, self.node.kind()) {
+            (Cow::Borrowed(node), NodeKind::Sequence(_)) => {
+                node.as_sequence().unwrap().iter().map(Cow::Borrowed).collect()
+            },
+            (Cow::Owned(_), NodeKind::Sequence(sequence)) => {
+                sequence.iter().map(|node| Cow::Owned(node.clone())).collect()
+            },
+            (_, NodeKind::Null) => Vec::new(),
+            _ => vec![self.node.clone()],
+        }
+    }
+
+    /// Text of a scalar, which is used to read any scalar as a string.
+    pub(crate) fn text(&self) -> Option<String> {
+        match self.node.kind() {
+            NodeKind::Bool(_) | NodeKind::Integer(_) | NodeKind::Float(_) | NodeKind::String(_) => {
+                Some(self.node.to_string())
+            },
+            _ => None,
+        }
+    }
+}
+
+fn attribute_node(value: &str, owner: &Node) -> Node {
+    let mut node = Node::from_text(value);
+    node.set_position(owner.position());
+    node
+}
+
+impl<'de, 'a> de::Deserializer<'de> for NodeDeserializer<'a> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let path = self.path.clone();
+        let position = self.node.position();
+
+        if !self.node.attributes().is_empty() {
+            return self.deserialize_map(visitor);
+        }
+
+        let result = match self.node.kind() {
+            NodeKind::Null => visitor.visit_unit(),
+            NodeKind::Bool(boolean) => visitor.visit_bool(*boolean),
+            NodeKind::Integer(integer) => visitor.visit_i64(*integer),
+            NodeKind::Float(float) => visitor.visit_f64(*float),
+            NodeKind::String(string) => visitor.visit_str(string),
+            NodeKind::Sequence(_) => return self.deserialize_seq(visitor),
+            NodeKind::Map(_) => return self.deserialize_map(visitor),
+        };
+
+        NodeDeserializer::locate(&path, position, result)
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.text() {
+            Some(text) => {
+                let position = self.node.position();
+                NodeDeserializer::locate(&self.path, position, visitor.visit_string(text))
+            },
+            None => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        if self.node.is_null() && self.node.attributes().is_empty() {
+            let position = self.node.position();
+            NodeDeserializer::locate(&self.path, position, visitor.visit_none())
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let view = self.unwrap();
+        let position = view.node.position();
+        let elements = view.elements();
+        let access = SeqAccess {
+            elements: elements.into_iter().enumerate(),
+            path: view.path.clone(),
+        };
+
+        NodeDeserializer::locate(&view.path, position, visitor.visit_seq(access))
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let position = self.node.position();
+
+        let entries = match self.entries() {
+            Some(entries) => entries,
+            None => {
+                let result = match self.node.kind() {
+                    NodeKind::Sequence(_) => Err(de::Error::invalid_type(de::Unexpected::Seq, &visitor)),
+                    _ => Err(de::Error::invalid_type(unexpected(&self.node), &visitor)),
+                };
+                return NodeDeserializer::locate(&self.path, position, result);
+            },
+        };
+        let access = MapAccess {
+            entries: entries.into_iter(),
+            value: None,
+            path: self.path.clone(),
+        };
+
+        NodeDeserializer::locate(&self.path, position, visitor.visit_map(access))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    /// Only unit variants written as strings are supported.
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        let position = self.node.position();
+        let result = match self.text() {
+            Some(text) => visitor.visit_enum(text.into_deserializer()),
+            None => Err(de::Error::invalid_type(unexpected(&self.node), &"a variant name")),
+        };
+
+        NodeDeserializer::locate(&self.path, position, result)
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char
+        bytes byte_buf unit unit_struct tuple tuple_struct identifier ignored_any
+    }
+}
+
+fn unexpected(node: &Node) -> de::Unexpected<'_> {
+    match node.kind() {
+        NodeKind::Null => de::Unexpected::Unit,
+        NodeKind::Bool(boolean) => de::Unexpected::Bool(*boolean),
+        NodeKind::Integer(integer) => de::Unexpected::Signed(*integer),
+        NodeKind::Float(float) => de::Unexpected::Float(*float),
+        NodeKind::String(string) => de::Unexpected::Str(string),
+        NodeKind::Sequence(_) => de::Unexpected::Seq,
+        NodeKind::Map(_) => de::Unexpected::Map,
+    }
+}
+
+struct SeqAccess<'a, I: Iterator<Item = (usize, Cow<'a, Node>)>> {
+    elements: I,
+    path: String,
+}
+
+impl<'de, 'a, I: Iterator<Item = (usize, Cow<'a, Node>)>> de::SeqAccess<'de> for SeqAccess<'a, I> {
+    type Error = Error;
+
+    fn next_element_seed<T: de::DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>, Error> {
+        match self.elements.next() {
+            Some((i, node)) => {
+                let path = format!("{}[{}]", self.path, i);
+                seed.deserialize(NodeDeserializer::new(node, path)).map(Some)
+            },
+            None => Ok(None),
+        }
+    }
+}
+
+struct MapAccess<'a, I: Iterator<Item = (String, Cow<'a, Node>)>> {
+    entries: I,
+    value: Option<(String, Cow<'a, Node>)>,
+    path: String,
+}
+
+impl<'de, 'a, I: Iterator<Item = (String, Cow<'a, Node>)>> de::MapAccess<'de> for MapAccess<'a, I> {
+    type Error = Error;
+
+    fn next_key_seed<K: de::DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>, Error> {
+        match self.entries.next() {
+            Some((key, node)) => {
+                let value = seed.deserialize(key.as_str().into_deserializer()).map(Some);
+                self.value = Some((key, node));
+                value
+            },
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: de::DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        let (key, node) = self.value.take().ok_or_else(|| de::Error::custom("value is missing"))?;
+        let path = format!("{}.{}", self.path, key);
+
+        seed.deserialize(NodeDeserializer::new(node, path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct PhoneNumber {
+        #[serde(rename = "@type")]
+        kind: String,
+        #[serde(rename = "#text")]
+        number: String,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    enum Gender {
+        #[serde(rename = "male")]
+        Male,
+        #[serde(rename = "female")]
+        Female,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct Record {
+        id: String,
+        gender: Option<Gender>,
+        #[serde(deserialize_with = "from_wrapper")]
+        phones: Vec<PhoneNumber>,
+    }
+
+    fn xml_document(data: &str) -> Document {
+        Document::from_xml(&roxmltree::Document::parse(data).unwrap())
+    }
+
+    #[test]
+    fn lenient_xml() {
+        let document = xml_document(
+            r#"<record><id>007</id><gender>male</gender><phones><phone type="home">1</phone></phones></record>"#
+        );
+        let record: Record = Document::new(document.root()["record"].clone()).deserialize().unwrap();
+
+        assert_eq!(record.id, "007");
+        assert_eq!(record.gender, Some(Gender::Male));
+        assert_eq!(record.phones, vec![PhoneNumber { kind: String::from("home"), number: String::from("1") }]);
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Address {
+        city: String,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Resident {
+        address: Address,
+    }
+
+    #[test]
+    fn only_root_is_unwrapped() {
+        // A resident has only one child element with children of its own, but is not a wrapper.
+        let document = xml_document("<residents><resident><address><city>A</city></address></resident></residents>");
+        let residents: Vec<Resident> = document.deserialize().unwrap();
+        assert_eq!(residents, vec![Resident { address: Address { city: String::from("A") } }]);
+
+        let document = xml_document(r#"<record><id>1</id><phones><phone type="home">1</phone><fax>2</fax></phones></record>"#);
+        match Document::new(document.root()["record"].clone()).deserialize::<Record>() {
+            Err(DataExtractionError::Deserialize { path, message, .. }) => {
+                assert_eq!(path, "$.phones");
+                assert_eq!(message, "unexpected `fax` in a wrapper element");
+            },
+            other => panic!("Deserialize error is expected: {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn error_location() {
+        let document = xml_document("<record>\n  <id>1</id>\n  <gender>other</gender>\n  <phones/>\n</record>");
+
+        match Document::new(document.root()["record"].clone()).deserialize::<Record>() {
+            Err(DataExtractionError::Deserialize { path, line, column, .. }) => {
+                assert_eq!(path, "$.gender");
+                assert_eq!((line, column), (Some(3), Some(3)));
+            },
+            other => panic!("Deserialize error is expected: {:?}", other.map(|_| ())),
+        }
+    }
+}
@@ -0,0 +1,756 @@
+//! Format-neutral model of extracted data.
+//!
+//! Every extractor produces a `Document`, so callers don't need to know
+//! which format the data came from.
+//!
+//! # Mapping from XML
+//!
+//! * The root element becomes a map with a single entry named after it,
+//!   so `<persons>...</persons>` is read by `root()["persons"]`.
+//! * An element with child elements becomes a map keyed by the names of the children.
+//!   Children with the same name are put into a sequence.
+//! * An element without child elements becomes a scalar made from its text.
+//!   An empty element is null.
+//! * Attributes of an element become attributes of its node,
+//!   so `<phoneNumber type="home">` gives `attribute("type") == Some("home")`.
+//! * Text of an element which also has child elements is kept under the `#text` key.
+//! * Namespaces are ignored and only local names are used.
+//!
+//! Text is typed the same way as CSV fields (see `Node::from_text`),
+//! but writers of JSON and TOML write it back as strings since XML has no types.
+//!
+//! Since XML cannot tell a single child from a sequence of one child,
+//! use `Node::members` to read something which may be repeated.
+//!
+//! # Mapping from JSON
+//!
+//! JSON maps to the model as it is, except that an object key starting with `@`
+//! is an attribute and the `#text` key is the value of an object which has attributes.
+//! So `{"@type": "home", "#text": "212 555-1234"}` is the same node as
+//! `<phoneNumber type="home">212 555-1234</phoneNumber>`.
+//!
+//! # Positions
+//!
+//! Nodes made from JSON and XML remember where they are in the source,
+//! so errors found later can point at the file.
+//! Positions are not compared when nodes are compared.
+//!
+//! # Order
+//!
+//! Entries of maps are kept in the order they first appear in the source.
+//! Children of an XML element with the same name are put together where the first of them is.
+//! The order of entries is not compared when nodes are compared.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::ops::Index;
+
+use indexmap::IndexMap;
+use serde_json::Value;
+
+/// Key prefix for attributes in JSON.
+pub const ATTRIBUTE_PREFIX: &str = "@";
+/// Key for text of a node which has attributes or child elements.
+pub const TEXT_KEY: &str = "#text";
+
+/// Entries of a map node in the order of the source.
+pub type Map = IndexMap<String, Node>;
+
+static NULL: Node = Node {
+    kind: NodeKind::Null,
+    attributes: BTreeMap::new(),
+    position: None,
+    untyped: false,
+};
+
+/// Extracted data.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Document {
+    root: Node,
+}
+
+/// A node of a document.
+#[derive(Debug, Clone)]
+pub struct Node {
+    kind: NodeKind,
+    attributes: BTreeMap<String, String>,
+    position: Option<Position>,
+    /// The scalar was typed from untyped text such as XML text or a CSV field.
+    untyped: bool,
+}
+
+/// 1-based position in a source file.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// Value of a node.
+#[derive(Debug, Clone, PartialEq)]
+pub enum NodeKind {
+    Null,
+    Bool(bool),
+    Integer(i64),
+    Float(f64),
+    String(String),
+    Sequence(Vec<Node>),
+    Map(Map),
+}
+
+impl Document {
+    pub fn new(root: Node) -> Document {
+        Document { root }
+    }
+
+    pub fn root(&self) -> &Node {
+        &self.root
+    }
+
+    /// Make a document from JSON. Positions of nodes are unknown.
+    pub fn from_json(value: Value) -> Document {
+        Document::new(Node::from_json(value))
+    }
+
+    /// Make a document from JSON, and take positions of nodes from the source.
+    pub(crate) fn from_json_source(value: Value, source: &str) -> Document {
+        let mut root = Node::from_json(value);
+        let positions = json_positions(source);
+        root.set_json_positions(&mut Vec::new(), &positions);
+
+        Document::new(root)
+    }
+
+    pub(crate) fn from_xml(document: &roxmltree::Document) -> Document {
+        let root_element = document.root_element();
+        let mut root = Map::new();
+        root.insert(root_element.tag_name().name().to_string(), Node::from_xml(root_element));
+
+        Document::new(Node::new(NodeKind::Map(root)))
+    }
+
+    pub(crate) fn from_toml(value: toml::Value) -> Document {
+        Document::new(Node::from_toml(value))
+    }
+
+    pub(crate) fn into_root(self) -> Node {
+        self.root
+    }
+
+    /// Convert the document to JSON with the mapping described in the module.
+    pub fn to_json(&self) -> Value {
+        self.root.to_json()
+    }
+
+    /// Like `to_json`, but untyped scalars are strings as they were written.
+    pub(crate) fn to_json_as_written(&self) -> Value {
+        self.root.to_json_with(true)
+    }
+}
+
+impl Node {
+    pub fn new(kind: NodeKind) -> Node {
+        Node {
+            kind,
+            attributes: BTreeMap::new(),
+            position: None,
+            untyped: false,
+        }
+    }
+
+    pub fn with_attributes(kind: NodeKind, attributes: BTreeMap<String, String>) -> Node {
+        Node {
+            kind,
+            attributes,
+            position: None,
+            untyped: false,
+        }
+    }
+
+    /// Return where the node starts in the source, if it is known.
+    pub fn position(&self) -> Option<Position> {
+        self.position
+    }
+
+    pub(crate) fn set_position(&mut self, position: Option<Position>) {
+        self.position = position;
+    }
+
+    /// Move positions of the node and its descendants
+    /// as if `from` in the source were at `to`.
+    ///
+    /// This is used when a piece of a file is parsed by itself.
+    pub(crate) fn move_positions(&mut self, from: Position, to: Position) {
+        if let Some(position) = self.position {
+            self.position = Some(if position.line == from.line {
+                Position { line: to.line, column: position.column + to.column - from.column }
+            } else {
+                Position { line: position.line + to.line - from.line, column: position.column }
+            });
+        }
+
+        match &mut self.kind {
+            NodeKind::Sequence(sequence) => sequence.iter_mut().for_each(|node| node.move_positions(from, to)),
+            NodeKind::Map(map) => map.values_mut().for_each(|node| node.move_positions(from, to)),
+            _ => {},
+        }
+    }
+
+    /// Make a scalar from untyped text such as XML text or a CSV field.
+    ///
+    /// Empty text is null. Text is a number only when the number is written back
+    /// to the same text, so "10021" is a number but "007" and "1e3" are strings.
+    /// The node remembers that it was text (see `is_untyped`).
+    pub fn from_text(text: &str) -> Node {
+        let kind = if text.is_empty() {
+            NodeKind::Null
+        } else if let Some(integer) = text.parse::<i64>().ok().filter(|i| i.to_string() == text) {
+            NodeKind::Integer(integer)
+        } else if let Some(float) = text.parse::<f64>().ok().filter(|f| f.is_finite() && f.to_string() == text) {
+            NodeKind::Float(float)
+        } else {
+            NodeKind::String(text.to_string())
+        };
+
+        Node {
+            untyped: true,
+            ..Node::new(kind)
+        }
+    }
+
+    pub fn kind(&self) -> &NodeKind {
+        &self.kind
+    }
+
+    /// Return whether the node was typed from untyped text by `from_text`.
+    pub fn is_untyped(&self) -> bool {
+        self.untyped
+    }
+
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes.get(name).map(|value| value.as_str())
+    }
+
+    pub fn attributes(&self) -> &BTreeMap<String, String> {
+        &self.attributes
+    }
+
+    /// Return the entry of a map.
+    pub fn get(&self, key: &str) -> Option<&Node> {
+        match &self.kind {
+            NodeKind::Map(map) => map.get(key),
+            _ => None,
+        }
+    }
+
+    /// Return the elements of a sequence, or the node itself for others.
+    ///
+    /// Null has no members.
+    pub fn members(&self) -> Vec<&Node> {
+        match &self.kind {
+            NodeKind::Sequence(sequence) => sequence.iter().collect(),
+            NodeKind::Null => Vec::new(),
+            _ => vec![self],
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        self.kind == NodeKind::Null
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self.kind {
+            NodeKind::Bool(boolean) => Some(boolean),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match self.kind {
+            NodeKind::Integer(integer) => Some(integer),
+            _ => None,
+        }
+    }
+
+    /// Integers are also returned as floats.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self.kind {
+            NodeKind::Integer(integer) => Some(integer as f64),
+            NodeKind::Float(float) => Some(float),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match &self.kind {
+            NodeKind::String(string) => Some(string),
+            _ => None,
+        }
+    }
+
+    pub fn as_sequence(&self) -> Option<&[Node]> {
+        match &self.kind {
+            NodeKind::Sequence(sequence) => Some(sequence),
+            _ => None,
+        }
+    }
+
+    pub fn as_map(&self) -> Option<&Map> {
+        match &self.kind {
+            NodeKind::Map(map) => Some(map),
+            _ => None,
+        }
+    }
+
+    fn from_json(value: Value) -> Node {
+        match value {
+            Value::Null => Node::new(NodeKind::Null),
+            Value::Bool(boolean) => Node::new(NodeKind::Bool(boolean)),
+            Value::Number(number) => match number.as_i64() {
+                Some(integer) => Node::new(NodeKind::Integer(integer)),
+                None => Node::new(NodeKind::Float(number.as_f64().unwrap_or(0.0))),
+            },
+            Value::String(string) => Node::new(NodeKind::String(string)),
+            Value::Array(array) => {
+                Node::new(NodeKind::Sequence(array.into_iter().map(Node::from_json).collect()))
+            },
+            Value::Object(object) => {
+                let mut attributes = BTreeMap::new();
+                let mut map = Map::new();
+                let mut text = None;
+
+                for (key, value) in object {
+                    if let Some(name) = key.strip_prefix(ATTRIBUTE_PREFIX) {
+                        if let Some(attribute) = json_scalar_to_string(&value) {
+                            attributes.insert(name.to_string(), attribute);
+                            continue;
+                        }
+                    }
+                    if key == TEXT_KEY {
+                        text = Some(value);
+                        continue;
+                    }
+                    map.insert(key, Node::from_json(value));
+                }
+
+                match text {
+                    // `{"@type": "home", "#text": "..."}` is a scalar with attributes.
+                    Some(text) if map.is_empty() => {
+                        Node::with_attributes(Node::from_json(text).kind, attributes)
+                    },
+                    Some(text) => {
+                        map.insert(TEXT_KEY.to_string(), Node::from_json(text));
+                        Node::with_attributes(NodeKind::Map(map), attributes)
+                    },
+                    None => Node::with_attributes(NodeKind::Map(map), attributes),
+                }
+            },
+        }
+    }
+
+    fn from_xml(element: roxmltree::Node) -> Node {
+        let attributes = element.attributes()
+            .iter()
+            .map(|attr| (attr.name().to_string(), attr.value().to_string()))
+            .collect();
+
+        // Whitespace between elements is not regarded as text.
+        let text: String = element.children()
+            .filter(|child| child.is_text())
+            .filter_map(|child| child.text())
+            .collect();
+        let text = text.trim();
+
+        let pos = element.document().text_pos_at(element.range().start);
+        let position = Some(Position {
+            line: pos.row as usize,
+            column: pos.col as usize,
+        });
+
+        let mut children: IndexMap<String, Vec<Node>> = IndexMap::new();
+        for child in element.children().filter(|child| child.is_element()) {
+            children.entry(child.tag_name().name().to_string())
+                .or_default()
+                .push(Node::from_xml(child));
+        }
+
+        if children.is_empty() {
+            let mut node = Node::from_text(text);
+            node.attributes = attributes;
+            node.position = position;
+            return node;
+        }
+
+        let mut map: Map = children.into_iter()
+            .map(|(name, mut nodes)| {
+                let node = if nodes.len() == 1 {
+                    nodes.remove(0)
+                } else {
+                    let mut sequence = Node::new(NodeKind::Sequence(nodes));
+                    sequence.position = sequence[0].position;
+                    sequence
+                };
+                (name, node)
+            })
+            .collect();
+        if !text.is_empty() {
+            let mut text = Node::from_text(text);
+            text.position = position;
+            map.insert(TEXT_KEY.to_string(), text);
+        }
+
+        let mut node = Node::with_attributes(NodeKind::Map(map), attributes);
+        node.position = position;
+        node
+    }
+
+    fn set_json_positions(&mut self, path: &mut Vec<PathSegment>, positions: &HashMap<Vec<PathSegment>, Position>) {
+        self.position = positions.get(path).copied();
+
+        match &mut self.kind {
+            NodeKind::Sequence(sequence) => {
+                for (i, node) in sequence.iter_mut().enumerate() {
+                    path.push(PathSegment::Index(i));
+                    node.set_json_positions(path, positions);
+                    path.pop();
+                }
+            },
+            NodeKind::Map(map) => {
+                for (key, node) in map.iter_mut() {
+                    path.push(PathSegment::Key(key.clone()));
+                    node.set_json_positions(path, positions);
+                    path.pop();
+                }
+            },
+            _ => {},
+        }
+    }
+
+    /// Datetimes have no counterpart, so they become strings.
+    fn from_toml(value: toml::Value) -> Node {
+        let kind = match value {
+            toml::Value::String(string) => NodeKind::String(string),
+            toml::Value::Integer(integer) => NodeKind::Integer(integer),
+            toml::Value::Float(float) => NodeKind::Float(float),
+            toml::Value::Boolean(boolean) => NodeKind::Bool(boolean),
+            toml::Value::Datetime(datetime) => NodeKind::String(datetime.to_string()),
+            toml::Value::Array(array) => {
+                NodeKind::Sequence(array.into_iter().map(Node::from_toml).collect())
+            },
+            toml::Value::Table(table) => NodeKind::Map(
+                table.into_iter()
+                    .map(|(key, value)| (key, Node::from_toml(value)))
+                    .collect()
+            ),
+        };
+
+        Node::new(kind)
+    }
+
+    /// Convert the node to JSON with the mapping described in the module.
+    pub fn to_json(&self) -> Value {
+        self.to_json_with(false)
+    }
+
+    fn to_json_with(&self, as_written: bool) -> Value {
+        let value = match &self.kind {
+            NodeKind::Bool(_) | NodeKind::Integer(_) | NodeKind::Float(_) if as_written && self.untyped => {
+                Value::String(self.to_string())
+            },
+            NodeKind::Null => Value::Null,
+            NodeKind::Bool(boolean) => Value::Bool(*boolean),
+            NodeKind::Integer(integer) => Value::from(*integer),
+            NodeKind::Float(float) => serde_json::Number::from_f64(*float)
+                .map(Value::Number)
+                .unwrap_or(Value::Null),
+            NodeKind::String(string) => Value::String(string.clone()),
+            NodeKind::Sequence(sequence) => {
+                Value::Array(sequence.iter().map(|node| node.to_json_with(as_written)).collect())
+            },
+            NodeKind::Map(map) => Value::Object(
+                map.iter()
+                    .map(|(key, node)| (key.clone(), node.to_json_with(as_written)))
+                    .collect()
+            ),
+        };
+
+        if self.attributes.is_empty() {
+            return value;
+        }
+
+        let mut object = match value {
+            Value::Object(object) => object,
+            value => {
+                let mut object = serde_json::Map::new();
+                object.insert(TEXT_KEY.to_string(), value);
+                object
+            },
+        };
+        for (name, attribute) in &self.attributes {
+            object.insert(format!("{}{}", ATTRIBUTE_PREFIX, name), Value::String(attribute.clone()));
+        }
+
+        Value::Object(object)
+    }
+}
+
+/// Nodes are compared without their positions.
+impl PartialEq for Node {
+    fn eq(&self, other: &Node) -> bool {
+        self.kind == other.kind && self.attributes == other.attributes
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum PathSegment {
+    Key(String),
+    Index(usize),
+}
+
+/// Find where each value starts in JSON text.
+///
+/// serde_json doesn't keep positions in `Value`, so the text is scanned again.
+/// The text must be already parsed successfully.
+fn json_positions(source: &str) -> HashMap<Vec<PathSegment>, Position> {
+    let mut scanner = JsonScanner {
+        chars: source.chars().peekable(),
+        position: Position { line: 1, column: 1 },
+        positions: HashMap::new(),
+    };
+    scanner.scan_value(&mut Vec::new());
+    scanner.positions
+}
+
+struct JsonScanner<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+    position: Position,
+    positions: HashMap<Vec<PathSegment>, Position>,
+}
+
+impl<'a> JsonScanner<'a> {
+    fn next(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        if c == '\n' {
+            self.position.line += 1;
+            self.position.column = 1;
+        } else {
+            self.position.column += 1;
+        }
+        Some(c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.chars.peek().is_some_and(|c| c.is_whitespace() || *c == '\u{feff}') {
+            self.next();
+        }
+    }
+
+    fn scan_value(&mut self, path: &mut Vec<PathSegment>) {
+        self.skip_whitespace();
+        self.positions.insert(path.clone(), self.position);
+
+        match self.chars.peek() {
+            Some('{') => {
+                self.next();
+                loop {
+                    self.skip_whitespace();
+                    match self.chars.peek() {
+                        Some('"') => {},
+                        _ => {
+                            // '}' of an empty object.
+                            self.next();
+                            return;
+                        },
+                    }
+                    let key = self.scan_string();
+                    self.skip_whitespace();
+                    self.next(); // ':'
+
+                    path.push(PathSegment::Key(key));
+                    self.scan_value(path);
+                    path.pop();
+
+                    self.skip_whitespace();
+                    if self.next() != Some(',') {
+                        return;
+                    }
+                }
+            },
+            Some('[') => {
+                self.next();
+                let mut index = 0;
+                loop {
+                    self.skip_whitespace();
+                    if self.chars.peek() == Some(&']') {
+                        self.next();
+                        return;
+                    }
+
+                    path.push(PathSegment::Index(index));
+                    self.scan_value(path);
+                    path.pop();
+                    index += 1;
+
+                    self.skip_whitespace();
+                    if self.next() != Some(',') {
+                        return;
+                    }
+                }
+            },
+            Some('"') => {
+                self.scan_string();
+            },
+            _ => {
+                while self.chars.peek().is_some_and(|c| !(c.is_whitespace() || ",]}".contains(*c))) {
+                    self.next();
+                }
+            },
+        }
+    }
+
+    /// Scan a string and return it unescaped.
+    fn scan_string(&mut self) -> String {
+        let mut raw = String::new();
+        raw.push(self.next().unwrap_or('"'));
+
+        while let Some(c) = self.next() {
+            raw.push(c);
+            if c == '\\' {
+                if let Some(escaped) = self.next() {
+                    raw.push(escaped);
+                }
+            } else if c == '"' {
+                break;
+            }
+        }
+
+        serde_json::from_str(&raw).unwrap_or_default()
+    }
+}
+
+fn json_scalar_to_string(value: &Value) -> Option<String> {
+    match value {
+        Value::String(string) => Some(string.clone()),
+        Value::Bool(_) | Value::Number(_) => Some(value.to_string()),
+        _ => None,
+    }
+}
+
+/// Missing entries are null like `serde_json::Value`.
+impl Index<&str> for Node {
+    type Output = Node;
+
+    fn index(&self, key: &str) -> &Node {
+        self.get(key).unwrap_or(&NULL)
+    }
+}
+
+impl Index<usize> for Node {
+    type Output = Node;
+
+    fn index(&self, index: usize) -> &Node {
+        self.as_sequence()
+            .and_then(|sequence| sequence.get(index))
+            .unwrap_or(&NULL)
+    }
+}
+
+/// Scalars are shown as they are, and the others are shown as JSON.
+impl fmt::Display for Node {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.kind {
+            NodeKind::Null => write!(f, "null"),
+            NodeKind::Bool(boolean) => write!(f, "{}", boolean),
+            NodeKind::Integer(integer) => write!(f, "{}", integer),
+            NodeKind::Float(float) => write!(f, "{}", float),
+            NodeKind::String(string) => write!(f, "{}", string),
+            _ => write!(f, "{}", self.to_json()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn positions() {
+        let document = xml_document("<persons>\n  <person id=\"1\">\n    <age>25</age>\n  </person>\n</persons>");
+        let person = &document.root()["persons"]["person"];
+
+        assert_eq!(person.position(), Some(Position { line: 2, column: 3 }));
+        assert_eq!(person["age"].position(), Some(Position { line: 3, column: 5 }));
+
+        let source = "[\n {\"title\": \"A\\\"\", \"cast\": [\"x\", {\"y\": 1}]}\n]";
+        let document = Document::from_json_source(serde_json::from_str(source).unwrap(), source);
+        let movie = &document.root()[0];
+
+        assert_eq!(movie.position(), Some(Position { line: 2, column: 2 }));
+        assert_eq!(movie["title"].position(), Some(Position { line: 2, column: 12 }));
+        assert_eq!(movie["cast"][1]["y"].position(), Some(Position { line: 2, column: 39 }));
+    }
+
+    fn xml_document(data: &str) -> Document {
+        Document::from_xml(&roxmltree::Document::parse(data).unwrap())
+    }
+
+    fn json_document(data: &str) -> Document {
+        Document::from_json(serde_json::from_str(data).unwrap())
+    }
+
+    #[test]
+    fn text_to_scalar() {
+        assert_eq!(Node::from_text("").kind(), &NodeKind::Null);
+        assert_eq!(Node::from_text("10021").kind(), &NodeKind::Integer(10021));
+        assert_eq!(Node::from_text("7.5").kind(), &NodeKind::Float(7.5));
+        assert_eq!(Node::from_text("007").kind(), &NodeKind::String("007".to_string()));
+        assert_eq!(Node::from_text("212 555-1234").kind(), &NodeKind::String("212 555-1234".to_string()));
+    }
+
+    #[test]
+    fn xml_mapping() {
+        let document = xml_document(
+            r#"<person id="1"><name>John</name><tag>a</tag><tag>b</tag><note/></person>"#
+        );
+        let person = &document.root()["person"];
+
+        assert_eq!(person.attribute("id"), Some("1"));
+        assert_eq!(person["name"].as_str(), Some("John"));
+        assert_eq!(person["tag"].members().len(), 2);
+        assert!(person["note"].is_null());
+        assert!(person.get("note").is_some());
+    }
+
+    #[test]
+    fn xml_mixed_content() {
+        let document = xml_document(r#"<p>Hello <b>world</b></p>"#);
+
+        assert_eq!(document.root()["p"]["#text"].as_str(), Some("Hello"));
+        assert_eq!(document.root()["p"]["b"].as_str(), Some("world"));
+    }
+
+    #[test]
+    fn json_attributes() {
+        let from_xml = xml_document(r#"<phoneNumber type="home">212 555-1234</phoneNumber>"#);
+        let from_json = json_document(r##"{"phoneNumber": {"@type": "home", "#text": "212 555-1234"}}"##);
+
+        assert_eq!(from_xml, from_json);
+        assert_eq!(from_json.root()["phoneNumber"].attribute("type"), Some("home"));
+    }
+
+    #[test]
+    fn to_json_round_trip() {
+        let document = xml_document(r#"<a x="1"><b y="2">text</b><b>3</b></a>"#);
+
+        assert_eq!(Document::from_json(document.to_json()), document);
+    }
+
+    #[test]
+    fn members_of_single_and_sequence() {
+        let single = json_document(r#"{"phoneNumber": "212 555-1234"}"#);
+        let sequence = json_document(r#"{"phoneNumber": ["212 555-1234"]}"#);
+
+        assert_eq!(single.root()["phoneNumber"].members(), sequence.root()["phoneNumber"].members());
+        assert!(single.root()["missing"].members().is_empty());
+    }
+}
@@ -0,0 +1,135 @@
+//! Element view of XML documents.
+//!
+//! `XmlElement` reads a `Document` the way the XML it came from is written,
+//! so code written against elements keeps working on top of the format-neutral model.
+
+use std::collections::BTreeMap;
+
+use crate::document::{Document, Node, NodeKind, TEXT_KEY};
+
+/// An element of a document made from XML.
+///
+/// It borrows the node of the element, so it's cheap to copy.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct XmlElement<'a> {
+    name: &'a str,
+    node: &'a Node,
+}
+
+impl<'a> XmlElement<'a> {
+    /// Return the root element, the single entry of the root map.
+    pub fn root(document: &'a Document) -> Option<XmlElement<'a>> {
+        let map = document.root().as_map()?;
+        if map.len() != 1 {
+            return None;
+        }
+
+        let (name, node) = map.iter().next()?;
+        match node.kind() {
+            NodeKind::Sequence(_) => None,
+            _ => Some(XmlElement { name, node }),
+        }
+    }
+
+    pub fn name(&self) -> &'a str {
+        self.name
+    }
+
+    /// Return the node of the element.
+    pub fn node(&self) -> &'a Node {
+        self.node
+    }
+
+    /// Return the text of the element as it would be written in XML.
+    pub fn text(&self) -> Option<String> {
+        let text = match self.node.kind() {
+            NodeKind::Map(map) => map.get(TEXT_KEY)?,
+            NodeKind::Null => return None,
+            _ => self.node,
+        };
+
+        Some(text.to_string())
+    }
+
+    pub fn attribute(&self, name: &str) -> Option<&'a str> {
+        self.node.attribute(name)
+    }
+
+    pub fn attributes(&self) -> &'a BTreeMap<String, String> {
+        self.node.attributes()
+    }
+
+    /// Return the child elements in the order they are written.
+    ///
+    /// The document keeps children with the same name together (see the document module),
+    /// so they are put back in order by their positions. Without positions they stay grouped.
+    pub fn children(&self) -> Vec<XmlElement<'a>> {
+        let map = match self.node.as_map() {
+            Some(map) => map,
+            None => return Vec::new(),
+        };
+
+        let mut children = Vec::new();
+        for (name, node) in map.iter().filter(|(name, _)| *name != TEXT_KEY) {
+            match node.kind() {
+                NodeKind::Sequence(sequence) => {
+                    children.extend(sequence.iter().map(|node| XmlElement { name, node }));
+                },
+                _ => children.push(XmlElement { name, node }),
+            }
+        }
+
+        if children.iter().all(|child| child.node.position().is_some()) {
+            children.sort_by_key(|child| child.node.position().map(|position| (position.line, position.column)));
+        }
+        children
+    }
+
+    /// Return the first child element with the name.
+    pub fn find(&self, name: &str) -> Option<XmlElement<'a>> {
+        self.children().into_iter().find(|child| child.name == name)
+    }
+
+    /// Return all child elements with the name.
+    pub fn find_all(&self, name: &str) -> Vec<XmlElement<'a>> {
+        self.children().into_iter().filter(|child| child.name == name).collect()
+    }
+
+    /// Return all descendant elements with the name.
+    ///
+    /// This is the same as `.//name` of ElementTree in Python.
+    pub fn descendants(&self, name: &str) -> Vec<XmlElement<'a>> {
+        let mut found = Vec::new();
+
+        for child in self.children() {
+            if child.name == name {
+                found.push(child);
+            }
+            found.extend(child.descendants(name));
+        }
+
+        found
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn xml_document(data: &str) -> Document {
+        Document::from_xml(&roxmltree::Document::parse(data).unwrap())
+    }
+
+    #[test]
+    fn children_in_source_order() {
+        let document = xml_document("<a><c>1</c><b/><c>2</c>\n<d x=\"y\">text<e/></d></a>");
+        let root = XmlElement::root(&document).unwrap();
+        let names: Vec<&str> = root.children().iter().map(|child| child.name()).collect();
+
+        assert_eq!(names, vec!["c", "b", "c", "d"]);
+        assert_eq!(root.find("b").unwrap().text(), None);
+        assert_eq!(root.find_all("c")[1].text().as_deref(), Some("2"));
+        assert_eq!(root.find("d").unwrap().text().as_deref(), Some("text"));
+        assert_eq!(root.find("d").unwrap().attribute("x"), Some("y"));
+    }
+}
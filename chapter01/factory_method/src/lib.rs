@@ -1,52 +1,799 @@
-// Only JSON Extractor is implemented yet.
+// JSON, XML, CSV and TOML Extractors are implemented.
 //
 
+use std::error::Error;
+use std::fmt;
 use std::fs;
+use std::io;
+use std::io::Read;
+use std::path::Path;
 
+use serde::de::DeserializeOwned;
 use serde_json::Value;
 
+mod de;
+mod document;
+mod element;
+mod query;
+mod schema;
+mod stream;
+mod writer;
+
+pub use de::from_wrapper;
+pub use document::{Document, Node, NodeKind, Position};
+pub use element::XmlElement;
+pub use query::{Match, Query, QueryError};
+pub use schema::{Schema, Violation};
+pub use stream::DataStream;
+pub use writer::{data_writer_for, CsvDataWriter, DataWriter, JsonDataWriter, TomlDataWriter, XmlDataWriter};
+
+/// Formats which can be extracted and written.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DataFormat {
+    Json,
+    Xml,
+    Csv,
+    Toml,
+}
+
+/// Errors while extracting data from a file.
+///
+/// Positions are 1-based as the parsers report them.
+#[derive(Debug)]
+pub enum DataExtractionError {
+    /// The file cannot be read.
+    Io(io::Error),
+    /// The data is not valid JSON.
+    JsonSyntax { message: String, line: usize, column: usize },
+    /// The data is not valid XML.
+    XmlSyntax { message: String, line: usize, column: usize },
+    /// The data is not valid CSV. The csv crate doesn't give a column.
+    CsvSyntax { message: String, line: Option<usize> },
+    /// The data is not valid TOML.
+    TomlSyntax { message: String, line: Option<usize>, column: Option<usize> },
+    /// Neither the extension nor the content tells a supported format.
+    UnsupportedFormat(String),
+    /// The data doesn't fit the requested type.
+    /// `path` is in the query syntax, like `$[2].year`.
+    Deserialize { message: String, path: String, line: Option<usize>, column: Option<usize> },
+    /// The data has no shape in the format it's written in.
+    Unrepresentable { format: DataFormat, message: String },
+    /// The schema uses a keyword wrongly. `path` is where it is in the schema.
+    InvalidSchema { message: String, path: String },
+    /// The data doesn't match the schema.
+    Invalid(Vec<Violation>),
+}
+
+impl Error for DataExtractionError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            DataExtractionError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for DataExtractionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DataExtractionError::Io(e) => write!(f, "Cannot read the file: {}", e),
+            DataExtractionError::JsonSyntax { message, line, column } => {
+                write!(f, "Invalid JSON at line {} column {}: {}", line, column, message)
+            },
+            DataExtractionError::XmlSyntax { message, line, column } => {
+                write!(f, "Invalid XML at line {} column {}: {}", line, column, message)
+            },
+            DataExtractionError::CsvSyntax { message, line } => match line {
+                Some(line) => write!(f, "Invalid CSV at line {}: {}", line, message),
+                None => write!(f, "Invalid CSV: {}", message),
+            },
+            DataExtractionError::TomlSyntax { message, .. } => {
+                // The message of the toml crate already contains the position.
+                write!(f, "Invalid TOML: {}", message)
+            },
+            DataExtractionError::UnsupportedFormat(filepath) => {
+                write!(f, "Cannot extract data from {}", filepath)
+            },
+            DataExtractionError::Deserialize { message, path, line, column } => match (line, column) {
+                (Some(line), Some(column)) => {
+                    write!(f, "Invalid {} at line {} column {}: {}", path, line, column, message)
+                },
+                _ => write!(f, "Invalid {}: {}", path, message),
+            },
+            DataExtractionError::Unrepresentable { format, message } => {
+                write!(f, "Cannot write as {:?}: {}", format, message)
+            },
+            DataExtractionError::InvalidSchema { message, path } => {
+                write!(f, "Invalid schema at {}: {}", path, message)
+            },
+            DataExtractionError::Invalid(violations) => {
+                write!(f, "The data doesn't match the schema:")?;
+                for violation in violations {
+                    write!(f, "\n  {}", violation)?;
+                }
+                Ok(())
+            },
+        }
+    }
+}
+
+impl From<io::Error> for DataExtractionError {
+    fn from(e: io::Error) -> DataExtractionError {
+        DataExtractionError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for DataExtractionError {
+    fn from(e: serde_json::Error) -> DataExtractionError {
+        DataExtractionError::JsonSyntax {
+            message: e.to_string(),
+            line: e.line(),
+            column: e.column(),
+        }
+    }
+}
+
+impl From<roxmltree::Error> for DataExtractionError {
+    fn from(e: roxmltree::Error) -> DataExtractionError {
+        let pos = e.pos();
+
+        DataExtractionError::XmlSyntax {
+            message: e.to_string(),
+            line: pos.row as usize,
+            column: pos.col as usize,
+        }
+    }
+}
+
+impl From<csv::Error> for DataExtractionError {
+    fn from(e: csv::Error) -> DataExtractionError {
+        DataExtractionError::CsvSyntax {
+            line: e.position().map(|pos| pos.line() as usize),
+            message: e.to_string(),
+        }
+    }
+}
+
+impl From<toml::de::Error> for DataExtractionError {
+    fn from(e: toml::de::Error) -> DataExtractionError {
+        // The toml crate counts lines and columns from 0.
+        let line_col = e.line_col();
+
+        DataExtractionError::TomlSyntax {
+            message: e.to_string(),
+            line: line_col.map(|(line, _)| line + 1),
+            column: line_col.map(|(_, column)| column + 1),
+        }
+    }
+}
+
+/// Common interface of extractors created by `extract_data_from`.
+pub trait DataExtractor {
+    fn format(&self) -> DataFormat;
+
+    /// Return the data in the format-neutral model.
+    fn parsed_data(&self) -> &Document;
+
+    /// Select data with a path like `$.persons.person[?lastName=='Smith']`.
+    ///
+    /// See the `query` module for the syntax.
+    fn query(&self, query: &str) -> Result<Vec<Match<'_>>, QueryError> {
+        self.parsed_data().query(query)
+    }
+
+    /// Check the data with a schema. See the `schema` module for what is supported.
+    fn validate(&self, schema: &Schema) -> Vec<Violation> {
+        schema.validate(self.parsed_data())
+    }
+
+    fn print_data(&self) {
+        // Serializing a Value never fails.
+        println!("{}", serde_json::to_string_pretty(&self.parsed_data().to_json()).unwrap());
+    }
+}
+
+impl dyn DataExtractor + '_ {
+    /// Deserialize the data into a user type like `Vec<Movie>`.
+    ///
+    /// A generic method cannot be in the trait used as a trait object,
+    /// so use `parse_data().deserialize()` for a concrete extractor.
+    /// See the `de` module for how data is read.
+    pub fn deserialize<T: DeserializeOwned>(&self) -> Result<T, DataExtractionError> {
+        self.parsed_data().deserialize()
+    }
+}
+
 pub struct JsonDataExtractor {
-    parsed_data: Value,
+    parsed_data: Document,
+}
+
+/// Extractor for XML.
+///
+/// See the `document` module for how elements and attributes are mapped.
+pub struct XmlDataExtractor {
+    parsed_data: Document,
+}
+
+/// Extractor for CSV with a header row.
+///
+/// Each record becomes a map keyed by the header,
+/// and all the records are put into a sequence like movies.json.
+pub struct CsvDataExtractor {
+    parsed_data: Document,
 }
 
-pub struct XmlDataExtractor {}
+/// Extractor for TOML.
+pub struct TomlDataExtractor {
+    parsed_data: Document,
+}
 
 impl JsonDataExtractor {
-    pub fn new(data: &String) -> JsonDataExtractor {
-        JsonDataExtractor {
-            parsed_data: serde_json::from_str(data).unwrap(),
+    pub fn new(data: &str) -> Result<JsonDataExtractor, DataExtractionError> {
+        let json_data: Value = serde_json::from_str(data)?;
+
+        Ok(JsonDataExtractor {
+            parsed_data: Document::from_json_source(json_data, data),
+        })
+    }
+
+    pub fn parse_data(&self) -> &Document {
+        &self.parsed_data
+    }
+}
+
+impl DataExtractor for JsonDataExtractor {
+    fn format(&self) -> DataFormat {
+        DataFormat::Json
+    }
+
+    fn parsed_data(&self) -> &Document {
+        &self.parsed_data
+    }
+}
+
+impl XmlDataExtractor {
+    pub fn new(data: &str) -> Result<XmlDataExtractor, DataExtractionError> {
+        let document = roxmltree::Document::parse(data)?;
+
+        Ok(XmlDataExtractor {
+            parsed_data: Document::from_xml(&document),
+        })
+    }
+
+    pub fn parse_data(&self) -> &Document {
+        &self.parsed_data
+    }
+
+    /// Return the root element to read the document as XML.
+    pub fn root_element(&self) -> XmlElement<'_> {
+        XmlElement::root(&self.parsed_data).expect("XML document has a single root element")
+    }
+}
+
+impl DataExtractor for XmlDataExtractor {
+    fn format(&self) -> DataFormat {
+        DataFormat::Xml
+    }
+
+    fn parsed_data(&self) -> &Document {
+        &self.parsed_data
+    }
+}
+
+impl CsvDataExtractor {
+    pub fn new(data: &str) -> Result<CsvDataExtractor, DataExtractionError> {
+        let mut reader = csv::Reader::from_reader(data.as_bytes());
+        let headers = reader.headers()?.clone();
+        let mut records = Vec::new();
+
+        for record in reader.records() {
+            records.push(node_from_csv_record(&headers, &record?));
         }
+
+        Ok(CsvDataExtractor {
+            parsed_data: Document::new(Node::new(NodeKind::Sequence(records))),
+        })
     }
 
-    pub fn parse_data(&self) -> &Value {
+    pub fn parse_data(&self) -> &Document {
         &self.parsed_data
     }
+}
+
+/// Make a map keyed by the header from a record.
+fn node_from_csv_record(headers: &csv::StringRecord, record: &csv::StringRecord) -> Node {
+    let row = headers.iter()
+        .zip(record.iter())
+        .map(|(key, field)| (key.to_string(), Node::from_text(field)))
+        .collect();
+
+    let mut node = Node::new(NodeKind::Map(row));
+    node.set_position(record.position().map(|position| Position {
+        line: position.line() as usize,
+        column: 1,
+    }));
+    node
+}
+
+impl DataExtractor for CsvDataExtractor {
+    fn format(&self) -> DataFormat {
+        DataFormat::Csv
+    }
 
-    pub fn print_data(&self) {
-        println!("{}",serde_json::to_string_pretty(&self.parsed_data).unwrap());
+    fn parsed_data(&self) -> &Document {
+        &self.parsed_data
     }
 }
 
-impl XmlDataExtractor {}
+impl TomlDataExtractor {
+    pub fn new(data: &str) -> Result<TomlDataExtractor, DataExtractionError> {
+        let toml_data: toml::Value = toml::from_str(data)?;
+
+        Ok(TomlDataExtractor {
+            parsed_data: Document::from_toml(toml_data),
+        })
+    }
+
+    pub fn parse_data(&self) -> &Document {
+        &self.parsed_data
+    }
+}
 
-fn data_extraction_factory_for_json(filepath: String) -> JsonDataExtractor {
-    let data = get_string_from_file(filepath);
+impl DataExtractor for TomlDataExtractor {
+    fn format(&self) -> DataFormat {
+        DataFormat::Toml
+    }
+
+    fn parsed_data(&self) -> &Document {
+        &self.parsed_data
+    }
+}
+
+impl DataFormat {
+    /// Guess a format from the extension of a file path.
+    pub fn from_extension(filepath: &str) -> Option<DataFormat> {
+        DataFormat::from_name(Path::new(filepath).extension()?.to_str()?)
+    }
+
+    /// Get a format from its name like `json`, case-insensitively.
+    pub fn from_name(name: &str) -> Option<DataFormat> {
+        match name.to_lowercase().as_str() {
+            "json" => Some(DataFormat::Json),
+            "xml" => Some(DataFormat::Xml),
+            "csv" => Some(DataFormat::Csv),
+            "toml" => Some(DataFormat::Toml),
+            _ => None,
+        }
+    }
+
+    /// Guess a format from the first meaningful character of data.
+    ///
+    /// CSV and TOML cannot be told from the first character,
+    /// so they are only recognized by the extension.
+    fn from_content(data: &str) -> Option<DataFormat> {
+        let first = data.trim_start_matches('\u{feff}').trim_start().chars().next()?;
+
+        match first {
+            '{' | '[' => Some(DataFormat::Json),
+            '<' => Some(DataFormat::Xml),
+            _ => None,
+        }
+    }
+}
+
+fn data_extraction_factory(filepath: String) -> Result<Box<dyn DataExtractor>, DataExtractionError> {
+    let data = get_string_from_file(&filepath)?;
+
+    // The extension is trusted first, and the content is checked
+    // only when the extension says nothing.
+    let format = DataFormat::from_extension(&filepath).or_else(|| DataFormat::from_content(&data));
+
+    match format {
+        Some(DataFormat::Json) => Ok(Box::new(JsonDataExtractor::new(&data)?)),
+        Some(DataFormat::Xml) => Ok(Box::new(XmlDataExtractor::new(&data)?)),
+        Some(DataFormat::Csv) => Ok(Box::new(CsvDataExtractor::new(&data)?)),
+        Some(DataFormat::Toml) => Ok(Box::new(TomlDataExtractor::new(&data)?)),
+        None => Err(DataExtractionError::UnsupportedFormat(filepath)),
+    }
+}
+
+/// Create an extractor suitable for the file.
+pub fn extract_data_from(filepath: String) -> Result<Box<dyn DataExtractor>, DataExtractionError> {
+    data_extraction_factory(filepath)
+}
+
+/// Create an extractor suitable for the file, and reject the file
+/// if its data doesn't match the schema.
+pub fn extract_data_from_with_schema(filepath: String, schema: &Schema) -> Result<Box<dyn DataExtractor>, DataExtractionError> {
+    let factory = data_extraction_factory(filepath)?;
+    let violations = factory.validate(schema);
+
+    if violations.is_empty() {
+        Ok(factory)
+    } else {
+        Err(DataExtractionError::Invalid(violations))
+    }
+}
+
+/// Create a stream of elements suitable for the file.
+///
+/// Unlike `extract_data_from`, the file is not read at once.
+/// See the `stream` module for what is yielded. TOML cannot be streamed.
+pub fn stream_data_from(filepath: String) -> Result<DataStream, DataExtractionError> {
+    let format = match DataFormat::from_extension(&filepath) {
+        Some(format) => Some(format),
+        None => DataFormat::from_content(&get_head_from_file(&filepath)?),
+    };
+
+    match format {
+        Some(DataFormat::Json) => Ok(Box::new(stream::JsonStream::open(&filepath)?)),
+        Some(DataFormat::Xml) => Ok(Box::new(stream::XmlStream::open(&filepath)?)),
+        Some(DataFormat::Csv) => Ok(Box::new(stream::CsvStream::open(&filepath)?)),
+        Some(DataFormat::Toml) | None => Err(DataExtractionError::UnsupportedFormat(filepath)),
+    }
+}
+
+fn data_extraction_factory_for_json(filepath: String) -> Result<JsonDataExtractor, DataExtractionError> {
+    let data = get_string_from_file(&filepath)?;
 
     JsonDataExtractor::new(&data)
 }
 
-pub fn extract_data_from_for_json(filepath: String) -> JsonDataExtractor {
+fn data_extraction_factory_for_xml(filepath: String) -> Result<XmlDataExtractor, DataExtractionError> {
+    let data = get_string_from_file(&filepath)?;
+
+    XmlDataExtractor::new(&data)
+}
+
+fn data_extraction_factory_for_csv(filepath: String) -> Result<CsvDataExtractor, DataExtractionError> {
+    let data = get_string_from_file(&filepath)?;
+
+    CsvDataExtractor::new(&data)
+}
+
+fn data_extraction_factory_for_toml(filepath: String) -> Result<TomlDataExtractor, DataExtractionError> {
+    let data = get_string_from_file(&filepath)?;
+
+    TomlDataExtractor::new(&data)
+}
+
+pub fn extract_data_from_for_json(filepath: String) -> Result<JsonDataExtractor, DataExtractionError> {
     data_extraction_factory_for_json(filepath)
 }
 
-fn get_string_from_file(filepath: String) -> String {
-    fs::read_to_string(filepath).expect("Something went wrong while reading the file")
+pub fn extract_data_from_for_xml(filepath: String) -> Result<XmlDataExtractor, DataExtractionError> {
+    data_extraction_factory_for_xml(filepath)
+}
+
+pub fn extract_data_from_for_csv(filepath: String) -> Result<CsvDataExtractor, DataExtractionError> {
+    data_extraction_factory_for_csv(filepath)
+}
+
+pub fn extract_data_from_for_toml(filepath: String) -> Result<TomlDataExtractor, DataExtractionError> {
+    data_extraction_factory_for_toml(filepath)
+}
+
+fn get_string_from_file(filepath: &str) -> Result<String, DataExtractionError> {
+    Ok(fs::read_to_string(filepath)?)
+}
+
+/// Read the beginning of a file, which is enough to guess the format.
+fn get_head_from_file(filepath: &str) -> Result<String, DataExtractionError> {
+    let mut head = Vec::new();
+    fs::File::open(filepath)?.take(1024).read_to_end(&mut head)?;
+
+    Ok(String::from_utf8_lossy(&head).into_owned())
 }
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Movie {
+        title: String,
+        year: Option<u32>,
+        director: Option<String>,
+        cast: Option<String>,
+        genre: Option<String>,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    #[serde(rename_all = "camelCase")]
+    struct Person {
+        first_name: String,
+        last_name: String,
+        age: u32,
+        address: Address,
+        #[serde(deserialize_with = "from_wrapper")]
+        phone_numbers: Vec<PhoneNumber>,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    #[serde(rename_all = "camelCase")]
+    struct Address {
+        street_address: String,
+        city: String,
+        state: String,
+        postal_code: String,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct PhoneNumber {
+        #[serde(rename = "@type")]
+        kind: String,
+        #[serde(rename = "#text")]
+        number: String,
+    }
+
+    /// Write a file with a name unique to the test run, keeping the extension of `name`.
+    pub(crate) fn temp_file(name: &str, data: &str) -> String {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+        let unique = format!("factory_method_{}_{}_{}", std::process::id(), COUNT.fetch_add(1, Ordering::SeqCst), name);
+        let filepath = std::env::temp_dir().join(unique);
+        fs::write(&filepath, data).unwrap();
+        filepath.to_str().unwrap().to_string()
+    }
+
     #[test]
     fn it_works() {
         assert_eq!(2 + 2, 4);
     }
+
+    #[test]
+    fn xml_element_lookup() {
+        let xml_factory = extract_data_from_for_xml(String::from("data/person.xml")).unwrap();
+        let persons = &xml_factory.parse_data().root()["persons"];
+
+        assert_eq!(persons["person"].members().len(), 3);
+
+        let john = &persons["person"][0];
+        assert_eq!(john["firstName"].as_str(), Some("John"));
+        assert_eq!(john["age"].as_i64(), Some(25));
+        assert_eq!(john["address"]["city"].as_str(), Some("New York"));
+        assert!(john["address"].as_map().is_some());
+        assert!(john["nickname"].is_null());
+    }
+
+    #[test]
+    fn xml_element_api() {
+        let xml_factory = extract_data_from_for_xml(String::from("data/person.xml")).unwrap();
+        let persons = xml_factory.root_element();
+
+        assert_eq!(persons.name(), "persons");
+        assert_eq!(persons.find_all("person").len(), 3);
+
+        let john = persons.find("person").unwrap();
+        assert_eq!(john.find("firstName").unwrap().text().as_deref(), Some("John"));
+        assert_eq!(john.find("address").unwrap().text(), None);
+        assert_eq!(john.find("address").unwrap().find("city").unwrap().text().as_deref(), Some("New York"));
+        assert!(john.find("nickname").is_none());
+    }
+
+    #[test]
+    fn xml_descendants() {
+        let xml_factory = extract_data_from_for_xml(String::from("data/person.xml")).unwrap();
+        let phone_numbers = xml_factory.root_element().descendants("phoneNumber");
+
+        assert_eq!(phone_numbers.len(), 5);
+        assert_eq!(phone_numbers[4].attribute("type"), Some("mobile"));
+    }
+
+    #[test]
+    fn xml_attribute_access() {
+        let xml_factory = XmlDataExtractor::new(
+            r#"<phoneNumbers><phoneNumber type="home">212 555-1234</phoneNumber></phoneNumbers>"#
+        ).unwrap();
+        let phone_number = &xml_factory.parse_data().root()["phoneNumbers"]["phoneNumber"];
+
+        assert_eq!(phone_number.attribute("type"), Some("home"));
+        assert_eq!(phone_number.attribute("kind"), None);
+        assert_eq!(phone_number.as_str(), Some("212 555-1234"));
+    }
+
+    #[test]
+    fn factory_by_extension() {
+        let json_factory = extract_data_from(String::from("data/movies.json")).unwrap();
+        assert_eq!(json_factory.format(), DataFormat::Json);
+
+        let xml_factory = extract_data_from(String::from("data/person.xml")).unwrap();
+        assert_eq!(xml_factory.format(), DataFormat::Xml);
+
+        assert!(xml_factory.parsed_data().root().get("persons").is_some());
+
+        let csv_factory = extract_data_from(String::from("data/movies.csv")).unwrap();
+        assert_eq!(csv_factory.format(), DataFormat::Csv);
+    }
+
+    #[test]
+    fn csv_same_as_json() {
+        let json_factory = extract_data_from(String::from("data/movies.json")).unwrap();
+        let csv_factory = extract_data_from(String::from("data/movies.csv")).unwrap();
+
+        assert_eq!(json_factory.parsed_data(), csv_factory.parsed_data());
+    }
+
+    #[test]
+    fn csv_field_types() {
+        let csv_factory = CsvDataExtractor::new("title,year,rating\nCaught,1900,7.5\n\"Hats, Clowns\",,\n").unwrap();
+        let csv_data = csv_factory.parse_data().root();
+
+        assert_eq!(csv_data[0]["year"].as_i64(), Some(1900));
+        assert_eq!(csv_data[0]["rating"].as_f64(), Some(7.5));
+        assert_eq!(csv_data[1]["title"].as_str(), Some("Hats, Clowns"));
+        assert!(csv_data[1]["year"].is_null());
+    }
+
+    #[test]
+    fn toml_to_document() {
+        let toml_factory = TomlDataExtractor::new(
+            "[[movies]]\ntitle = \"Caught\"\nyear = 1900\nreleased = 1900-01-01\n"
+        ).unwrap();
+        let movie = &toml_factory.parse_data().root()["movies"][0];
+
+        assert_eq!(movie["title"].as_str(), Some("Caught"));
+        assert_eq!(movie["year"].as_i64(), Some(1900));
+        assert_eq!(movie["released"].as_str(), Some("1900-01-01"));
+    }
+
+    #[test]
+    fn error_for_csv_and_toml_syntax() {
+        match CsvDataExtractor::new("title,year\nCaught,1900\nFamily Troubles\n") {
+            Err(DataExtractionError::CsvSyntax { line, .. }) => assert_eq!(line, Some(3)),
+            _ => panic!("CsvSyntax error is expected"),
+        }
+
+        match TomlDataExtractor::new("title = \"Caught\"\nyear = \n") {
+            Err(DataExtractionError::TomlSyntax { line, .. }) => assert_eq!(line, Some(2)),
+            _ => panic!("TomlSyntax error is expected"),
+        }
+    }
+
+    #[test]
+    fn factory_by_content() {
+        assert_eq!(DataFormat::from_extension("data/movies.txt"), None);
+        assert_eq!(DataFormat::from_extension("data/MOVIES.JSON"), Some(DataFormat::Json));
+
+        assert_eq!(DataFormat::from_content(" \n [1, 2]"), Some(DataFormat::Json));
+        assert_eq!(DataFormat::from_content("\u{feff}<?xml version=\"1.0\"?><a/>"), Some(DataFormat::Xml));
+        assert_eq!(DataFormat::from_content("SQLite format 3"), None);
+        assert_eq!(DataFormat::from_content(""), None);
+    }
+
+    #[test]
+    fn error_for_json_syntax() {
+        match JsonDataExtractor::new("[\n {\"title\": }\n]") {
+            Err(DataExtractionError::JsonSyntax { line, column, .. }) => {
+                assert_eq!((line, column), (2, 12));
+            },
+            _ => panic!("JsonSyntax error is expected"),
+        }
+    }
+
+    #[test]
+    fn error_for_xml_syntax() {
+        match XmlDataExtractor::new("<persons>\n  <person></persons>") {
+            Err(DataExtractionError::XmlSyntax { line, .. }) => assert_eq!(line, 2),
+            _ => panic!("XmlSyntax error is expected"),
+        }
+    }
+
+    #[test]
+    fn error_for_unsupported_format() {
+        let filepath = temp_file("person.sq3", "SQLite format 3");
+
+        match extract_data_from(filepath.clone()) {
+            Err(DataExtractionError::UnsupportedFormat(unsupported)) => {
+                assert_eq!(unsupported, filepath);
+            },
+            _ => panic!("UnsupportedFormat error is expected"),
+        }
+    }
+
+    #[test]
+    fn factory_by_content_without_extension() {
+        let filepath = temp_file("movies", "[{\"title\": \"Caught\"}]");
+        assert_eq!(extract_data_from(filepath).unwrap().format(), DataFormat::Json);
+
+        match extract_data_from(String::from("data/person.sq3")) {
+            Err(DataExtractionError::UnsupportedFormat(_)) => {},
+            _ => panic!("UnsupportedFormat error is expected"),
+        }
+    }
+
+    #[test]
+    fn error_for_missing_file() {
+        match extract_data_from(String::from("data/missing.json")) {
+            Err(DataExtractionError::Io(e)) => assert_eq!(e.kind(), io::ErrorKind::NotFound),
+            _ => panic!("Io error is expected"),
+        }
+    }
+
+    #[test]
+    fn query_xml_and_json() {
+        let xml_factory = extract_data_from(String::from("data/person.xml")).unwrap();
+        let json_factory = extract_data_from(String::from("data/person.json")).unwrap();
+
+        for query in &[
+            "$.persons.person[?lastName=='Smith'].phoneNumbers",
+            "$.persons.person[?lastName=='Liar'].phoneNumbers.phoneNumber",
+            "$..phoneNumber[?@type=='home'].@type",
+            "$.persons.person[?age>19 && gender.type=='female'].firstName",
+        ] {
+            let xml_matches = xml_factory.query(query).unwrap();
+            assert!(!xml_matches.is_empty());
+            assert_eq!(xml_matches, json_factory.query(query).unwrap());
+        }
+
+        let found = xml_factory.query("$.persons.person[?lastName=='Liar'].phoneNumbers.phoneNumber").unwrap();
+        assert_eq!(found.len(), 3);
+        assert_eq!(found[2].as_node().unwrap().attribute("type"), Some("mobile"));
+    }
+
+    #[test]
+    fn deserialize_movies() {
+        let json_factory = extract_data_from(String::from("data/movies.json")).unwrap();
+        let movies = json_factory.deserialize::<Vec<Movie>>().unwrap();
+
+        assert_eq!(movies.len(), 9);
+        assert_eq!(movies[5].director, Some(String::from("James H. White")));
+        assert_eq!(movies[0].genre, None);
+
+        let csv_factory = extract_data_from_for_csv(String::from("data/movies.csv")).unwrap();
+        assert_eq!(csv_factory.parse_data().deserialize::<Vec<Movie>>().unwrap(), movies);
+    }
+
+    #[test]
+    fn deserialize_persons() {
+        let xml_factory = extract_data_from(String::from("data/person.xml")).unwrap();
+        let persons = xml_factory.deserialize::<Vec<Person>>().unwrap();
+
+        assert_eq!(persons.len(), 3);
+        assert_eq!(persons[0].address.postal_code, "10021");
+        assert_eq!(persons[1].phone_numbers.len(), 1);
+        assert_eq!(persons[2].phone_numbers[1], PhoneNumber {
+            kind: String::from("mobile"),
+            number: String::from("001 452-8819"),
+        });
+
+        let json_factory = extract_data_from(String::from("data/person.json")).unwrap();
+        assert_eq!(json_factory.deserialize::<Vec<Person>>().unwrap(), persons);
+    }
+
+    #[test]
+    fn deserialize_error_location() {
+        let json_factory = JsonDataExtractor::new("[\n {\"title\": \"Caught\",\n  \"year\": \"1900\"}\n]").unwrap();
+
+        match json_factory.parse_data().deserialize::<Vec<Movie>>() {
+            Err(DataExtractionError::Deserialize { path, line, column, .. }) => {
+                assert_eq!(path, "$[0].year");
+                assert_eq!((line, column), (Some(3), Some(11)));
+            },
+            _ => panic!("Deserialize error is expected"),
+        }
+
+        let xml_factory = XmlDataExtractor::new("<persons>\n  <person>\n    <firstName>John</firstName>\n  </person>\n</persons>").unwrap();
+
+        match xml_factory.parse_data().deserialize::<Vec<Person>>() {
+            Err(DataExtractionError::Deserialize { path, message, line, .. }) => {
+                assert_eq!(path, "$.persons.person[0]");
+                assert!(message.contains("lastName"));
+                assert_eq!(line, Some(2));
+            },
+            _ => panic!("Deserialize error is expected"),
+        }
+    }
+
+    #[test]
+    fn xml_same_as_json() {
+        let xml_factory = extract_data_from(String::from("data/person.xml")).unwrap();
+        let json_factory = extract_data_from(String::from("data/person.json")).unwrap();
+
+        assert_eq!(xml_factory.parsed_data(), json_factory.parsed_data());
+    }
 }
@@ -1,18 +1,157 @@
-// Only JSON Extractor is implemented yet.
-//
+use std::env;
+use std::fs;
+use std::process;
 
-use factory_method::extract_data_from_for_json;
+use factory_method::{data_writer_for, extract_data_from, extract_data_from_with_schema, DataExtractor, DataFormat, Schema};
+
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+struct Movie {
+    title: String,
+    year: Option<u32>,
+    director: Option<String>,
+    genre: Option<String>,
+}
+
+fn print_movies(factory: &dyn DataExtractor) {
+    let movies = match factory.deserialize::<Vec<Movie>>() {
+        Ok(movies) => movies,
+        Err(e) => {
+            println!("{}\n", e);
+            return;
+        },
+    };
+
+    println!("Found: {}", movies.len());
+    for movie in movies {
+        println!("Title: {}", movie.title);
+        if let Some(year) = movie.year { println!("Year: {}", year) };
+        if let Some(director) = movie.director { println!("Director: {}", director) };
+        if let Some(genre) = movie.genre { println!("Genre: {}", genre) };
+        println!();
+    }
+}
+
+fn print_liars(factory: &dyn DataExtractor) {
+    // Same as `findall(".//person[lastName='Liar']")` in Python.
+    let liars = factory.query("$..person[?lastName=='Liar']").unwrap();
+
+    println!("found: {} persons", liars.len());
+    for liar in liars.iter().filter_map(|liar| liar.as_node()) {
+        println!("first name: {}", liar["firstName"]);
+        println!("last name: {}", liar["lastName"]);
+        for p in liar.query("$.phoneNumbers.phoneNumber").unwrap() {
+            let phone_type = p.as_node().and_then(|node| node.attribute("type")).unwrap_or("");
+            println!("phone number ({}): {}", phone_type, p);
+        }
+        println!();
+    }
+    println!();
+}
+
+const USAGE: &str = "Usage: factory_method [--input FILE [--schema FILE] [--output FILE] [--to json|xml|csv|toml]]";
+
+struct Conversion {
+    input: String,
+    schema: Option<String>,
+    output: Option<String>,
+    /// Nothing is written when it's `None`, which only validates the input.
+    to: Option<DataFormat>,
+}
+
+fn parse_args(args: &[String]) -> Result<Conversion, String> {
+    let mut input = None;
+    let mut schema = None;
+    let mut output = None;
+    let mut to = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().cloned().ok_or(format!("{} needs a value", arg));
+        match arg.as_str() {
+            "--input" => input = Some(value()?),
+            "--schema" => schema = Some(value()?),
+            "--output" => output = Some(value()?),
+            "--to" => {
+                let name = value()?;
+                to = Some(DataFormat::from_name(&name).ok_or(format!("Unknown format: {}", name))?);
+            },
+            _ => return Err(format!("Unknown argument: {}", arg)),
+        }
+    }
+
+    let input = input.ok_or("--input is required")?;
+    // The format is told by the output file when it's not given.
+    let to = match (to, &output) {
+        (Some(to), _) => Some(to),
+        (None, Some(output)) => Some(DataFormat::from_extension(output).ok_or("--to is required for the output file")?),
+        (None, None) if schema.is_some() => None,
+        (None, None) => return Err("--to, --output or --schema is required".to_string()),
+    };
+
+    Ok(Conversion { input, schema, output, to })
+}
+
+fn convert(conversion: &Conversion) -> Result<(), Box<dyn std::error::Error>> {
+    // Invalid data is rejected before it's written.
+    let factory = match &conversion.schema {
+        Some(schema) => extract_data_from_with_schema(conversion.input.clone(), &Schema::from_file(schema.clone())?)?,
+        None => extract_data_from(conversion.input.clone())?,
+    };
+    let to = match conversion.to {
+        Some(to) => to,
+        None => {
+            println!("{} is valid", conversion.input);
+            return Ok(());
+        },
+    };
+    let data = data_writer_for(to).write(factory.parsed_data())?;
+
+    match &conversion.output {
+        Some(output) => fs::write(output, data)?,
+        None => print!("{}", data),
+    }
+    Ok(())
+}
+
+fn demo() {
+    // The caller doesn't care which extractor is created.
+    for filepath in &["data/person.sq3", "data/movies.json", "data/movies.csv", "data/person.xml"] {
+        let factory = match extract_data_from(filepath.to_string()) {
+            Ok(factory) => factory,
+            Err(e) => {
+                println!("{}\n", e);
+                continue;
+            },
+        };
+
+        if factory.parsed_data().root().as_sequence().is_some() {
+            print_movies(factory.as_ref());
+        } else {
+            print_liars(factory.as_ref());
+        }
+    }
+}
 
 fn main() {
-    let json_factory = extract_data_from_for_json(String::from("data/movies.json"));
-    let json_data = json_factory.parse_data();
-
-    println!("Found: {}", json_data.as_array().unwrap().len());
-    for movie in json_data.as_array().unwrap() {
-        println!("Title: {}", movie["title"]);
-        if !movie["year"].is_null() { println!("Year: {}", movie["year"]) };
-        if !movie["director"].is_null() { println!("Director: {}", movie["director"]) };
-        if !movie["genre"].is_null() { println!("Genre: {}", movie["genre"]) };
-        println!("");
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    // Without arguments, the extractors are demonstrated.
+    if args.is_empty() {
+        demo();
+        return;
+    }
+
+    let conversion = match parse_args(&args) {
+        Ok(conversion) => conversion,
+        Err(e) => {
+            eprintln!("{}\n{}", e, USAGE);
+            process::exit(2);
+        },
+    };
+    if let Err(e) = convert(&conversion) {
+        eprintln!("{}", e);
+        process::exit(1);
     }
 }
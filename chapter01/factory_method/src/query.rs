@@ -0,0 +1,655 @@
+//! Path queries over extracted data.
+//!
+//! The syntax is a small subset of JSONPath:
+//!
+//! * `$` is the root and every query starts with it.
+//! * `.name` or `['name']` selects an entry of a map.
+//! * `.@name` selects an attribute.
+//! * `..name` selects entries with the name at any depth.
+//! * `.*` or `[*]` selects all entries of a map.
+//! * `[2]` selects the third node under each parent, and `[-1]` selects the last one.
+//! * `[?cond]` keeps nodes for which the condition holds, for example
+//!   `[?lastName=='Smith']`, `[?age>=20 && @type!='fax']` or `[?director]`.
+//!   A condition is true when any node selected by its relative path
+//!   satisfies it, and a path without a comparison tests that a node exists.
+//!
+//! Like XPath, a query works on a set of nodes, and sequences are always
+//! expanded into their elements.
+//! So `$.persons.person` gives each person rather than a sequence,
+//! `$.persons.person.phoneNumbers.phoneNumber[0]` gives the first phone number of each person,
+//! `$` gives each movie of movies.json,
+//! and a single child of an XML element is treated the same as a sequence
+//! of one element in JSON.
+//! Null nodes are never matched.
+
+use std::cmp::Ordering;
+use std::error::Error;
+use std::fmt;
+
+use crate::document::{Document, Node, NodeKind};
+
+/// A node matched by a query.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Match<'a> {
+    Node(&'a Node),
+    /// Value of an attribute.
+    Attribute(&'a str),
+}
+
+/// Error for an invalid query.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryError {
+    message: String,
+    /// Character offset of the query where the error is found.
+    position: usize,
+}
+
+/// A parsed query.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query {
+    steps: Vec<Step>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Step {
+    Child(String),
+    Attribute(String),
+    Descendant(String),
+    Wildcard,
+    Index(i64),
+    Filter(Condition),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Condition {
+    Exists(Vec<Step>),
+    Compare(Vec<Step>, Operator, Literal),
+    And(Box<Condition>, Box<Condition>),
+    Or(Box<Condition>, Box<Condition>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Operator {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Literal {
+    String(String),
+    Number(f64),
+    Bool(bool),
+}
+
+impl<'a> Match<'a> {
+    pub fn as_node(&self) -> Option<&'a Node> {
+        match self {
+            Match::Node(node) => Some(node),
+            Match::Attribute(_) => None,
+        }
+    }
+
+    /// Strings of nodes and values of attributes are returned.
+    pub fn as_str(&self) -> Option<&'a str> {
+        match self {
+            Match::Node(node) => node.as_str(),
+            Match::Attribute(value) => Some(value),
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Match::Node(node) => node.as_i64(),
+            Match::Attribute(value) => value.parse().ok(),
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Match::Node(node) => node.as_f64(),
+            Match::Attribute(value) => value.parse().ok(),
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Match::Node(node) => node.as_bool(),
+            Match::Attribute(value) => value.parse().ok(),
+        }
+    }
+}
+
+impl<'a> fmt::Display for Match<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Match::Node(node) => write!(f, "{}", node),
+            Match::Attribute(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+impl QueryError {
+    fn new(message: &str, position: usize) -> QueryError {
+        QueryError {
+            message: message.to_string(),
+            position,
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    pub fn position(&self) -> usize {
+        self.position
+    }
+}
+
+impl Error for QueryError {}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid query at {}: {}", self.position, self.message)
+    }
+}
+
+impl Query {
+    pub fn parse(query: &str) -> Result<Query, QueryError> {
+        let mut parser = Parser {
+            chars: query.chars().collect(),
+            position: 0,
+        };
+
+        parser.expect('$')?;
+        let steps = parser.parse_steps(Vec::new(), false)?;
+
+        Ok(Query { steps })
+    }
+
+    pub fn evaluate<'a>(&self, document: &'a Document) -> Vec<Match<'a>> {
+        self.evaluate_node(document.root())
+    }
+
+    /// Evaluate the query with a node as `$`.
+    pub fn evaluate_node<'a>(&self, node: &'a Node) -> Vec<Match<'a>> {
+        evaluate_steps(&self.steps, vec![node])
+    }
+}
+
+impl Document {
+    /// Parse and evaluate a query.
+    pub fn query(&self, query: &str) -> Result<Vec<Match<'_>>, QueryError> {
+        Ok(Query::parse(query)?.evaluate(self))
+    }
+}
+
+impl Node {
+    /// Parse and evaluate a query with the node as `$`.
+    pub fn query(&self, query: &str) -> Result<Vec<Match<'_>>, QueryError> {
+        Ok(Query::parse(query)?.evaluate_node(self))
+    }
+}
+
+/// Nodes are kept in groups, one for the nodes selected under each parent,
+/// so that `[n]` selects from the children of each parent like XPath.
+fn evaluate_steps<'a>(steps: &[Step], nodes: Vec<&'a Node>) -> Vec<Match<'a>> {
+    let mut groups = vec![expand(nodes)];
+
+    for step in steps {
+        // Attributes have nothing under them,
+        // so the parser only allows them at the end of a path.
+        if let Step::Attribute(name) = step {
+            return groups.into_iter()
+                .flatten()
+                .filter_map(|node| node.attribute(name))
+                .map(Match::Attribute)
+                .collect();
+        }
+
+        groups = match step {
+            Step::Child(name) => groups.into_iter()
+                .flatten()
+                .map(|node| expand(node.get(name).into_iter().collect()))
+                .collect(),
+            Step::Descendant(name) => {
+                let mut found = Vec::new();
+                for node in groups.into_iter().flatten() {
+                    collect_descendants(node, name, &mut found);
+                }
+                found.into_iter().map(|node| expand(vec![node])).collect()
+            },
+            Step::Wildcard => groups.into_iter()
+                .flatten()
+                .filter_map(|node| node.as_map())
+                .map(|map| expand(map.values().collect()))
+                .collect(),
+            Step::Index(index) => groups.into_iter()
+                .map(|nodes| {
+                    let index = if *index < 0 { nodes.len() as i64 + index } else { *index };
+                    if index >= 0 {
+                        nodes.get(index as usize).map(|node| vec![*node]).unwrap_or_default()
+                    } else {
+                        Vec::new()
+                    }
+                })
+                .collect(),
+            Step::Filter(condition) => groups.into_iter()
+                .map(|nodes| nodes.into_iter().filter(|node| condition.holds(node)).collect())
+                .collect(),
+            Step::Attribute(_) => unreachable!(),
+        };
+    }
+
+    groups.into_iter().flatten().map(Match::Node).collect()
+}
+
+/// Expand sequences into their elements and drop nulls.
+fn expand(nodes: Vec<&Node>) -> Vec<&Node> {
+    nodes.into_iter()
+        .flat_map(|node| node.members())
+        .filter(|node| !node.is_null())
+        .collect()
+}
+
+fn collect_descendants<'a>(node: &'a Node, name: &str, found: &mut Vec<&'a Node>) {
+    match node.kind() {
+        NodeKind::Map(map) => {
+            for (key, child) in map {
+                if key == name {
+                    found.push(child);
+                }
+                collect_descendants(child, name, found);
+            }
+        },
+        NodeKind::Sequence(sequence) => {
+            for child in sequence {
+                collect_descendants(child, name, found);
+            }
+        },
+        _ => {},
+    }
+}
+
+impl Condition {
+    fn holds(&self, node: &Node) -> bool {
+        match self {
+            Condition::Exists(path) => !evaluate_steps(path, vec![node]).is_empty(),
+            Condition::Compare(path, operator, literal) => {
+                evaluate_steps(path, vec![node])
+                    .iter()
+                    .any(|found| compare(found, literal).is_some_and(|ordering| operator.accepts(ordering)))
+            },
+            Condition::And(left, right) => left.holds(node) && right.holds(node),
+            Condition::Or(left, right) => left.holds(node) || right.holds(node),
+        }
+    }
+}
+
+impl Operator {
+    fn accepts(self, ordering: Ordering) -> bool {
+        match self {
+            Operator::Eq => ordering == Ordering::Equal,
+            Operator::Ne => ordering != Ordering::Equal,
+            Operator::Lt => ordering == Ordering::Less,
+            Operator::Le => ordering != Ordering::Greater,
+            Operator::Gt => ordering == Ordering::Greater,
+            Operator::Ge => ordering != Ordering::Less,
+        }
+    }
+}
+
+/// Compare a matched value with a literal.
+///
+/// Values of different types cannot be compared.
+/// Attributes are typed like XML text, so `@id==1` works.
+fn compare(found: &Match, literal: &Literal) -> Option<Ordering> {
+    let attribute;
+    let node = match found {
+        Match::Node(node) => *node,
+        Match::Attribute(value) => {
+            attribute = Node::from_text(value);
+            &attribute
+        },
+    };
+
+    match (node.kind(), literal) {
+        (NodeKind::String(string), Literal::String(literal)) => Some(string.as_str().cmp(literal.as_str())),
+        (NodeKind::Bool(boolean), Literal::Bool(literal)) => Some(boolean.cmp(literal)),
+        (_, Literal::Number(literal)) => node.as_f64()?.partial_cmp(literal),
+        _ => None,
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.position + offset).copied()
+    }
+
+    fn error(&self, message: &str) -> QueryError {
+        QueryError::new(message, self.position)
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), QueryError> {
+        match self.peek() {
+            Some(c) if c == expected => {
+                self.position += 1;
+                Ok(())
+            },
+            Some(c) => Err(self.error(&format!("expected '{}' but found '{}'", expected, c))),
+            None => Err(self.error(&format!("expected '{}' but the query ended", expected))),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.position += 1;
+        }
+    }
+
+    /// Parse steps after `steps` until the end of the query,
+    /// or until something which cannot be a step in a condition.
+    fn parse_steps(&mut self, mut steps: Vec<Step>, in_condition: bool) -> Result<Vec<Step>, QueryError> {
+        loop {
+            if let Some(Step::Attribute(_)) = steps.last() {
+                if self.peek() == Some('.') || self.peek() == Some('[') {
+                    return Err(self.error("nothing can follow an attribute"));
+                }
+            }
+
+            match self.peek() {
+                Some('.') if self.peek_at(1) == Some('.') => {
+                    self.position += 2;
+                    steps.push(Step::Descendant(self.parse_name()?));
+                },
+                Some('.') => {
+                    self.position += 1;
+                    match self.peek() {
+                        Some('*') => {
+                            self.position += 1;
+                            steps.push(Step::Wildcard);
+                        },
+                        Some('@') => {
+                            self.position += 1;
+                            steps.push(Step::Attribute(self.parse_name()?));
+                        },
+                        _ => steps.push(Step::Child(self.parse_name()?)),
+                    }
+                },
+                Some('[') => {
+                    self.position += 1;
+                    steps.push(self.parse_bracket()?);
+                    self.expect(']')?;
+                },
+                None => return Ok(steps),
+                Some(_) if in_condition => return Ok(steps),
+                Some(c) => return Err(self.error(&format!("unexpected '{}'", c))),
+            }
+        }
+    }
+
+    fn parse_name(&mut self) -> Result<String, QueryError> {
+        let start = self.position;
+
+        while self.peek().is_some_and(|c| c.is_alphanumeric() || c == '_' || c == '-' || c == '#') {
+            self.position += 1;
+        }
+
+        if self.position == start {
+            return Err(self.error("expected a name"));
+        }
+
+        Ok(self.chars[start..self.position].iter().collect())
+    }
+
+    fn parse_bracket(&mut self) -> Result<Step, QueryError> {
+        self.skip_whitespace();
+
+        let step = match self.peek() {
+            Some('*') => {
+                self.position += 1;
+                Step::Wildcard
+            },
+            Some('?') => {
+                self.position += 1;
+                Step::Filter(self.parse_or()?)
+            },
+            Some('\'') | Some('"') => Step::Child(self.parse_string()?),
+            Some(c) if c == '-' || c.is_ascii_digit() => {
+                let start = self.position;
+                let number = self.parse_number()?;
+                if number.fract() != 0.0 {
+                    return Err(QueryError::new("an index must be an integer", start));
+                }
+                Step::Index(number as i64)
+            },
+            Some(c) => return Err(self.error(&format!("unexpected '{}' in brackets", c))),
+            None => return Err(self.error("the query ended in brackets")),
+        };
+
+        self.skip_whitespace();
+        Ok(step)
+    }
+
+    fn parse_or(&mut self) -> Result<Condition, QueryError> {
+        let mut condition = self.parse_and()?;
+
+        while self.peek() == Some('|') && self.peek_at(1) == Some('|') {
+            self.position += 2;
+            condition = Condition::Or(Box::new(condition), Box::new(self.parse_and()?));
+        }
+
+        Ok(condition)
+    }
+
+    fn parse_and(&mut self) -> Result<Condition, QueryError> {
+        let mut condition = self.parse_comparison()?;
+
+        while self.peek() == Some('&') && self.peek_at(1) == Some('&') {
+            self.position += 2;
+            condition = Condition::And(Box::new(condition), Box::new(self.parse_comparison()?));
+        }
+
+        Ok(condition)
+    }
+
+    fn parse_comparison(&mut self) -> Result<Condition, QueryError> {
+        self.skip_whitespace();
+        let path = self.parse_relative_path()?;
+        self.skip_whitespace();
+
+        let operator = match (self.peek(), self.peek_at(1)) {
+            (Some('='), Some('=')) => Operator::Eq,
+            (Some('!'), Some('=')) => Operator::Ne,
+            (Some('<'), Some('=')) => Operator::Le,
+            (Some('>'), Some('=')) => Operator::Ge,
+            (Some('<'), _) => Operator::Lt,
+            (Some('>'), _) => Operator::Gt,
+            _ => return Ok(Condition::Exists(path)),
+        };
+        self.position += match operator {
+            Operator::Lt | Operator::Gt => 1,
+            _ => 2,
+        };
+
+        self.skip_whitespace();
+        let literal = self.parse_literal()?;
+        self.skip_whitespace();
+
+        Ok(Condition::Compare(path, operator, literal))
+    }
+
+    /// A relative path is like `address.city` or `@type` without `$`.
+    fn parse_relative_path(&mut self) -> Result<Vec<Step>, QueryError> {
+        let first = match self.peek() {
+            Some('@') => {
+                self.position += 1;
+                Step::Attribute(self.parse_name()?)
+            },
+            _ => Step::Child(self.parse_name()?),
+        };
+
+        self.parse_steps(vec![first], true)
+    }
+
+    fn parse_literal(&mut self) -> Result<Literal, QueryError> {
+        match self.peek() {
+            Some('\'') | Some('"') => Ok(Literal::String(self.parse_string()?)),
+            Some(c) if c == '-' || c.is_ascii_digit() => Ok(Literal::Number(self.parse_number()?)),
+            Some(c) if c.is_alphabetic() => {
+                let start = self.position;
+                match self.parse_name()?.as_str() {
+                    "true" => Ok(Literal::Bool(true)),
+                    "false" => Ok(Literal::Bool(false)),
+                    word => Err(QueryError::new(&format!("unknown literal '{}'", word), start)),
+                }
+            },
+            Some(c) => Err(self.error(&format!("expected a literal but found '{}'", c))),
+            None => Err(self.error("expected a literal but the query ended")),
+        }
+    }
+
+    fn parse_string(&mut self) -> Result<String, QueryError> {
+        let start = self.position;
+        let quote = self.chars[start];
+        self.position += 1;
+
+        let mut string = String::new();
+        loop {
+            match self.peek() {
+                Some(c) if c == quote => {
+                    self.position += 1;
+                    return Ok(string);
+                },
+                Some('\\') => {
+                    self.position += 1;
+                    match self.peek() {
+                        Some(c) => string.push(c),
+                        None => break,
+                    }
+                    self.position += 1;
+                },
+                Some(c) => {
+                    string.push(c);
+                    self.position += 1;
+                },
+                None => break,
+            }
+        }
+
+        Err(QueryError::new("unterminated string", start))
+    }
+
+    fn parse_number(&mut self) -> Result<f64, QueryError> {
+        let start = self.position;
+
+        if self.peek() == Some('-') {
+            self.position += 1;
+        }
+        while self.peek().is_some_and(|c| c.is_ascii_digit() || c == '.') {
+            self.position += 1;
+        }
+
+        let number: String = self.chars[start..self.position].iter().collect();
+        number.parse().map_err(|_| QueryError::new(&format!("invalid number '{}'", number), start))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn document(data: &str) -> Document {
+        Document::from_json(serde_json::from_str(data).unwrap())
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(Query::parse("persons").unwrap_err().position(), 0);
+        assert_eq!(Query::parse("$.").unwrap_err().position(), 2);
+        assert_eq!(Query::parse("$.person[?name=='John'").unwrap_err().position(), 22);
+        assert_eq!(Query::parse("$.person[?name=='John]").unwrap_err().position(), 16);
+        assert_eq!(Query::parse("$.person[?name==John]").unwrap_err().position(), 16);
+        assert_eq!(Query::parse("$.person[1.5]").unwrap_err().position(), 9);
+        assert_eq!(Query::parse("$.person.@id.name").unwrap_err().position(), 12);
+        assert_eq!(Query::parse("$..phoneNumber[?@type.x=='home']").unwrap_err().position(), 21);
+        assert_eq!(Query::parse("$..phoneNumber[?@type[0]]").unwrap_err().position(), 21);
+        assert_eq!(Query::parse("$ person").unwrap_err().position(), 1);
+    }
+
+    #[test]
+    fn select_children() {
+        let document = document(r#"{"a": {"b": [1, 2, null, 3]}, "c": "x"}"#);
+
+        let found = document.query("$.a.b").unwrap();
+        assert_eq!(found.iter().map(|m| m.as_i64().unwrap()).collect::<Vec<_>>(), vec![1, 2, 3]);
+
+        assert_eq!(document.query("$['c']").unwrap()[0].as_str(), Some("x"));
+        assert_eq!(document.query("$.a.b[-1]").unwrap()[0].as_i64(), Some(3));
+        assert_eq!(document.query("$.*").unwrap().len(), 2);
+        assert!(document.query("$.missing").unwrap().is_empty());
+    }
+
+    #[test]
+    fn index_under_each_parent() {
+        let document = document(r#"{"person": [
+            {"name": "A", "phone": ["1", "2"]},
+            {"name": "B", "phone": "3"},
+            {"name": "C", "phone": ["4", "5", "6"]}
+        ]}"#);
+        let found = |query: &str| -> Vec<String> {
+            document.query(query).unwrap().iter().map(|m| m.to_string()).collect()
+        };
+
+        assert_eq!(found("$.person.phone[0]"), vec!["1", "3", "4"]);
+        assert_eq!(found("$.person.phone[-1]"), vec!["2", "3", "6"]);
+        assert_eq!(found("$..phone[1]"), vec!["2", "5"]);
+        assert_eq!(found("$.person[1].name"), vec!["B"]);
+        assert_eq!(found("$.*[0].name"), vec!["A"]);
+    }
+
+    #[test]
+    fn filter_conditions() {
+        let document = document(r#"[
+            {"title": "A", "year": 1900, "director": null},
+            {"title": "B", "year": 1901, "director": "X"},
+            {"title": "C", "year": 1902, "director": "Y"}
+        ]"#);
+        let titles = |query: &str| -> Vec<String> {
+            document.query(query).unwrap().iter().map(|m| m.to_string()).collect()
+        };
+
+        assert_eq!(titles("$[?director].title"), vec!["B", "C"]);
+        assert_eq!(titles("$[?year > 1900].title"), vec!["B", "C"]);
+        assert_eq!(titles("$[?year<=1901 && director=='X'].title"), vec!["B"]);
+        assert_eq!(titles("$[?title=='A' || title=='C'].title"), vec!["A", "C"]);
+        assert_eq!(titles("$[?title!='A'][0].title"), vec!["B"]);
+        assert!(titles("$[?year=='1900']").is_empty());
+    }
+
+    #[test]
+    fn attributes_and_descendants() {
+        let document = document(r##"{"phoneNumbers": {"phoneNumber": [
+            {"@type": "home", "@id": "1", "#text": "212 555-1234"},
+            {"@type": "fax", "@id": "2", "#text": "646 555-4567"}
+        ]}}"##);
+
+        let found = document.query("$..phoneNumber[?@type=='fax']").unwrap();
+        assert_eq!(found[0].as_str(), Some("646 555-4567"));
+
+        let found = document.query("$..phoneNumber[?@id==1].@type").unwrap();
+        assert_eq!(found, vec![Match::Attribute("home")]);
+    }
+}
@@ -0,0 +1,480 @@
+//! Validation of extracted data with a subset of JSON Schema.
+//!
+//! Supported keywords are `type`, `enum`, `properties`, `required`,
+//! `additionalProperties` (only `true` or `false`), `items`,
+//! `minimum`, `maximum`, `minLength`, `maxLength`, `minItems` and `maxItems`.
+//! Other keywords such as `title` and `description` are ignored.
+//!
+//! Data is seen in the same way as `deserialize` reads it,
+//! so data which passes a schema can be deserialized into a matching type:
+//!
+//! * Any scalar made from CSV or XML text is a `string`, since they don't tell a number from text.
+//!   Numbers and booleans from JSON and TOML are not strings.
+//! * An `array` is a sequence. The root element is skipped like `deserialize` does,
+//!   and so is a wrapper element like `<phoneNumbers>` where the schema expects an array,
+//!   whose items are then at paths like `$.persons.person[0].phoneNumbers.phoneNumber[1]`.
+//!   A wrapper with a single child element is an array of one element,
+//!   but scalars and other maps are not arrays.
+//!   `items`, `minItems` and `maxItems` apply only to arrays.
+//! * Attributes are properties with the `@` prefix.
+//! * A null property counts as missing, since empty CSV fields and
+//!   empty XML elements are null. So "year must be an integer when it is present"
+//!   is an optional `year` of type `integer`.
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde_json::Value;
+
+use crate::de::NodeDeserializer;
+use crate::document::{Document, Node, NodeKind, Position};
+use crate::{get_string_from_file, DataExtractionError};
+
+/// A schema which documents are validated with.
+#[derive(Debug, Clone, Default)]
+pub struct Schema {
+    types: Option<Vec<String>>,
+    enumeration: Option<Vec<Value>>,
+    properties: BTreeMap<String, Schema>,
+    required: Vec<String>,
+    additional_properties: bool,
+    items: Option<Box<Schema>>,
+    minimum: Option<f64>,
+    maximum: Option<f64>,
+    min_length: Option<usize>,
+    max_length: Option<usize>,
+    min_items: Option<usize>,
+    max_items: Option<usize>,
+}
+
+/// A place where a document doesn't match a schema.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Violation {
+    /// Path to the node in the query syntax, like `$[2].year`.
+    pub path: String,
+    pub message: String,
+    /// Where the node is, or where its parent is if it's missing.
+    pub position: Option<Position>,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.position {
+            Some(position) => {
+                write!(f, "{} at line {} column {}: {}", self.path, position.line, position.column, self.message)
+            },
+            None => write!(f, "{}: {}", self.path, self.message),
+        }
+    }
+}
+
+const TYPES: [&str; 7] = ["null", "boolean", "integer", "number", "string", "array", "object"];
+
+fn invalid_schema(path: &str, message: &str) -> DataExtractionError {
+    DataExtractionError::InvalidSchema {
+        message: message.to_string(),
+        path: path.to_string(),
+    }
+}
+
+impl Schema {
+    /// Parse a schema written in JSON.
+    pub fn new(data: &str) -> Result<Schema, DataExtractionError> {
+        let value: Value = serde_json::from_str(data)?;
+        Schema::from_json(&value, "$")
+    }
+
+    pub fn from_file(filepath: String) -> Result<Schema, DataExtractionError> {
+        Schema::new(&get_string_from_file(&filepath)?)
+    }
+
+    /// Make a schema from JSON. `path` is where it is in the whole schema.
+    fn from_json(value: &Value, path: &str) -> Result<Schema, DataExtractionError> {
+        let object = match value {
+            Value::Object(object) => object,
+            // `true` accepts anything.
+            Value::Bool(true) => return Ok(Schema { additional_properties: true, ..Schema::default() }),
+            _ => return Err(invalid_schema(path, "a schema must be an object")),
+        };
+        let mut schema = Schema {
+            additional_properties: true,
+            ..Schema::default()
+        };
+
+        for (keyword, value) in object {
+            let path = format!("{}.{}", path, keyword);
+            let count = |value: &Value| {
+                value.as_u64()
+                    .map(|count| count as usize)
+                    .ok_or_else(|| invalid_schema(&path, "must be a non-negative integer"))
+            };
+            let number = |value: &Value| value.as_f64().ok_or_else(|| invalid_schema(&path, "must be a number"));
+
+            match keyword.as_str() {
+                "type" => {
+                    let types = match value {
+                        Value::String(name) => vec![name.clone()],
+                        Value::Array(names) => names.iter()
+                            .map(|name| name.as_str().map(String::from))
+                            .collect::<Option<_>>()
+                            .ok_or_else(|| invalid_schema(&path, "must be a type name or an array of them"))?,
+                        _ => return Err(invalid_schema(&path, "must be a type name or an array of them")),
+                    };
+                    if let Some(unknown) = types.iter().find(|name| !TYPES.contains(&name.as_str())) {
+                        return Err(invalid_schema(&path, &format!("unknown type `{}`", unknown)));
+                    }
+                    schema.types = Some(types);
+                },
+                "enum" => match value {
+                    Value::Array(values) => schema.enumeration = Some(values.clone()),
+                    _ => return Err(invalid_schema(&path, "must be an array")),
+                },
+                "properties" => match value {
+                    Value::Object(properties) => {
+                        for (name, property) in properties {
+                            let property = Schema::from_json(property, &format!("{}.{}", path, name))?;
+                            schema.properties.insert(name.clone(), property);
+                        }
+                    },
+                    _ => return Err(invalid_schema(&path, "must be an object")),
+                },
+                "required" => {
+                    schema.required = value.as_array()
+                        .and_then(|names| names.iter().map(|name| name.as_str().map(String::from)).collect())
+                        .ok_or_else(|| invalid_schema(&path, "must be an array of property names"))?;
+                },
+                "additionalProperties" => {
+                    schema.additional_properties = value.as_bool()
+                        .ok_or_else(|| invalid_schema(&path, "only true or false is supported"))?;
+                },
+                "items" => schema.items = Some(Box::new(Schema::from_json(value, &path)?)),
+                "minimum" => schema.minimum = Some(number(value)?),
+                "maximum" => schema.maximum = Some(number(value)?),
+                "minLength" => schema.min_length = Some(count(value)?),
+                "maxLength" => schema.max_length = Some(count(value)?),
+                "minItems" => schema.min_items = Some(count(value)?),
+                "maxItems" => schema.max_items = Some(count(value)?),
+                _ => {},
+            }
+        }
+
+        Ok(schema)
+    }
+
+    /// Validate a whole document. An empty list means the document is valid.
+    pub fn validate(&self, document: &Document) -> Vec<Violation> {
+        self.validate_node(document.root())
+    }
+
+    /// Validate a node, such as an element of a stream, as the root.
+    pub fn validate_node(&self, node: &Node) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        self.check(&NodeDeserializer::root(Cow::Borrowed(node)), node, "$", &mut violations);
+        violations
+    }
+
+    /// Whether a wrapper element can be skipped for the node, as an array is expected.
+    fn expects_array(&self) -> bool {
+        self.types.as_ref().is_some_and(|types| types.iter().any(|name| name == "array"))
+            || self.items.is_some() || self.min_items.is_some() || self.max_items.is_some()
+    }
+
+    fn view<'a>(&self, node: &'a Node, path: String) -> NodeDeserializer<'a> {
+        let view = NodeDeserializer::new(Cow::Borrowed(node), path);
+        if self.expects_array() {
+            view.wrapper()
+        } else {
+            view
+        }
+    }
+
+    fn check(&self, view: &NodeDeserializer, node: &Node, path: &str, violations: &mut Vec<Violation>) {
+        let mut violate = |message: String| violations.push(Violation {
+            path: path.to_string(),
+            message,
+            position: node.position(),
+        });
+
+        if let Some(types) = &self.types {
+            if !types.iter().any(|name| is_type(view, node, name)) {
+                violate(format!("expected {}, found {}", types.join(" or "), type_name(node)));
+                // Other keywords would only repeat the same problem.
+                return;
+            }
+        }
+
+        if let Some(values) = &self.enumeration {
+            let text = string_of(view, node);
+            let found = values.iter().any(|value| {
+                *value == node.to_json() || (value.as_str().is_some() && value.as_str() == text.as_deref())
+            });
+            if !found {
+                let values: Vec<String> = values.iter().map(Value::to_string).collect();
+                violate(format!("must be one of {}", values.join(", ")));
+            }
+        }
+
+        if let Some(number) = node.as_f64() {
+            if self.minimum.is_some_and(|minimum| number < minimum) {
+                violate(format!("must be at least {}", self.minimum.unwrap()));
+            }
+            if self.maximum.is_some_and(|maximum| number > maximum) {
+                violate(format!("must be at most {}", self.maximum.unwrap()));
+            }
+        }
+
+        if let Some(text) = string_of(view, node) {
+            let length = text.chars().count();
+            if self.min_length.is_some_and(|min_length| length < min_length) {
+                violate(format!("must be at least {} characters long", self.min_length.unwrap()));
+            }
+            if self.max_length.is_some_and(|max_length| length > max_length) {
+                violate(format!("must be at most {} characters long", self.max_length.unwrap()));
+            }
+        }
+
+        if (self.items.is_some() || self.min_items.is_some() || self.max_items.is_some()) && view.is_sequence() {
+            self.check_items(view, node, path, violations);
+        }
+        if !self.properties.is_empty() || !self.required.is_empty() || !self.additional_properties {
+            self.check_properties(view, node, path, violations);
+        }
+    }
+
+    fn check_items(&self, view: &NodeDeserializer, node: &Node, path: &str, violations: &mut Vec<Violation>) {
+        let view = view.clone().unwrap();
+        let elements = view.elements();
+        let mut violate = |message: String| violations.push(Violation {
+            path: path.to_string(),
+            message,
+            position: node.position(),
+        });
+
+        if self.min_items.is_some_and(|min_items| elements.len() < min_items) {
+            violate(format!("must have at least {} items", self.min_items.unwrap()));
+        }
+        if self.max_items.is_some_and(|max_items| elements.len() > max_items) {
+            violate(format!("must have at most {} items", self.max_items.unwrap()));
+        }
+
+        if let Some(items) = &self.items {
+            for (i, element) in elements.iter().enumerate() {
+                let path = format!("{}[{}]", view.path(), i);
+                items.check(&items.view(element, path.clone()), element, &path, violations);
+            }
+        }
+    }
+
+    fn check_properties(&self, view: &NodeDeserializer, node: &Node, path: &str, violations: &mut Vec<Violation>) {
+        // Something which cannot be deserialized into a struct has no properties.
+        let entries: BTreeMap<String, Cow<Node>> = match view.entries() {
+            Some(entries) => entries.into_iter().filter(|(_, entry)| !entry.is_null()).collect(),
+            None => {
+                violations.push(Violation {
+                    path: path.to_string(),
+                    message: format!("expected object, found {}", type_name(node)),
+                    position: node.position(),
+                });
+                return;
+            },
+        };
+
+        for name in self.required.iter().filter(|name| !entries.contains_key(*name)) {
+            violations.push(Violation {
+                path: format!("{}.{}", path, name),
+                message: String::from("is required"),
+                position: node.position(),
+            });
+        }
+
+        for (name, entry) in &entries {
+            let path = format!("{}.{}", path, name);
+            match self.properties.get(name) {
+                Some(property) => {
+                    property.check(&property.view(entry, path.clone()), entry, &path, violations);
+                },
+                None if !self.additional_properties => violations.push(Violation {
+                    path,
+                    message: String::from("is not allowed"),
+                    position: entry.position(),
+                }),
+                None => {},
+            }
+        }
+    }
+}
+
+fn is_type(view: &NodeDeserializer, node: &Node, name: &str) -> bool {
+    match name {
+        "null" => node.is_null() && node.attributes().is_empty(),
+        "boolean" => node.as_bool().is_some(),
+        "integer" => match node.kind() {
+            NodeKind::Integer(_) => true,
+            NodeKind::Float(float) => float.fract() == 0.0,
+            _ => false,
+        },
+        "number" => node.as_f64().is_some(),
+        "string" => string_of(view, node).is_some(),
+        "array" => view.is_sequence(),
+        "object" => view.entries().is_some(),
+        _ => false,
+    }
+}
+
+/// Text of a string, or of any scalar made from untyped text.
+fn string_of(view: &NodeDeserializer, node: &Node) -> Option<String> {
+    if node.as_str().is_some() || node.is_untyped() {
+        view.text()
+    } else {
+        None
+    }
+}
+
+/// Name of the type of a node for messages.
+fn type_name(node: &Node) -> String {
+    let name = match node.kind() {
+        NodeKind::Null => "null",
+        NodeKind::Bool(_) => "boolean",
+        NodeKind::Integer(_) => "integer",
+        NodeKind::Float(_) => "number",
+        NodeKind::String(_) => return format!("string {:?}", node.as_str().unwrap_or("")),
+        NodeKind::Sequence(_) => "array",
+        NodeKind::Map(_) => "object",
+    };
+    name.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{extract_data_from, XmlDataExtractor};
+
+    const MOVIE_SCHEMA: &str = r#"{
+        "type": "array",
+        "items": {
+            "type": "object",
+            "required": ["title"],
+            "properties": {
+                "title": {"type": "string", "minLength": 1},
+                "year": {"type": "integer", "minimum": 1888},
+                "genre": {"enum": ["Comedy", "Drama", "Short documentary"]}
+            }
+        }
+    }"#;
+
+    #[test]
+    fn movies_are_valid() {
+        let schema = Schema::new(MOVIE_SCHEMA).unwrap();
+
+        for filepath in &["data/movies.json", "data/movies.csv"] {
+            let movies = extract_data_from(filepath.to_string()).unwrap();
+            assert_eq!(schema.validate(movies.parsed_data()), Vec::new());
+        }
+    }
+
+    #[test]
+    fn violations_with_paths() {
+        let schema = Schema::new(MOVIE_SCHEMA).unwrap();
+        let movies = crate::JsonDataExtractor::new(r#"[
+            {"title": "A", "year": "1900"},
+            {"year": 1800, "genre": "Western"},
+            {"title": "", "year": null}
+        ]"#).unwrap();
+
+        let violations: Vec<String> = schema.validate(movies.parse_data())
+            .iter()
+            .map(Violation::to_string)
+            .collect();
+        assert_eq!(violations, vec![
+            "$[0].year at line 2 column 36: expected integer, found string \"1900\"",
+            "$[1].title at line 3 column 13: is required",
+            "$[1].genre at line 3 column 37: must be one of \"Comedy\", \"Drama\", \"Short documentary\"",
+            "$[1].year at line 3 column 22: must be at least 1888",
+            "$[2].title at line 4 column 23: must be at least 1 characters long",
+        ]);
+    }
+
+    #[test]
+    fn xml_persons() {
+        let schema = Schema::new(r##"{
+            "type": "array",
+            "items": {
+                "required": ["firstName", "age", "phoneNumbers"],
+                "additionalProperties": false,
+                "properties": {
+                    "firstName": {"type": "string"},
+                    "lastName": {"type": "string"},
+                    "age": {"type": "integer"},
+                    "phoneNumbers": {
+                        "type": "array",
+                        "items": {"required": ["@type", "#text"], "properties": {"@type": {"enum": ["home", "fax"]}}}
+                    }
+                }
+            }
+        }"##).unwrap();
+        let persons = XmlDataExtractor::new(r#"<persons>
+            <person><firstName>A</firstName><age>1</age><phoneNumbers><phone type="fax">1</phone></phoneNumbers></person>
+            <person><firstName>B</firstName><age>x</age><phoneNumbers><phone type="mobile">2</phone><phone>3</phone></phoneNumbers><pet/></person>
+        </persons>"#).unwrap();
+
+        let violations: Vec<String> = schema.validate(persons.parse_data())
+            .into_iter()
+            .map(|violation| format!("{}: {}", violation.path, violation.message))
+            .collect();
+        assert_eq!(violations, vec![
+            "$.persons.person[1].age: expected integer, found string \"x\"",
+            "$.persons.person[1].phoneNumbers.phone[0].@type: must be one of \"home\", \"fax\"",
+            "$.persons.person[1].phoneNumbers.phone[1]: expected object, found integer",
+        ]);
+    }
+
+    #[test]
+    fn arrays_only() {
+        let schema = Schema::new(r#"{"properties": {"phones": {"type": "array"}}}"#).unwrap();
+        let messages = |data: &str| -> Vec<String> {
+            let document = crate::JsonDataExtractor::new(data).unwrap();
+            schema.validate(document.parse_data()).into_iter().map(|violation| violation.message).collect()
+        };
+
+        assert_eq!(messages(r#"{"phones": ["1", "2"]}"#), Vec::<String>::new());
+        assert_eq!(messages(r##"{"phones": {"phone": {"@type": "home", "#text": "1"}}}"##), Vec::<String>::new());
+        assert_eq!(messages(r#"{"phones": "1"}"#), vec!["expected array, found string \"1\""]);
+        assert_eq!(messages(r#"{"phones": 1}"#), vec!["expected array, found integer"]);
+        assert_eq!(messages(r#"{"phones": {"home": "1", "fax": "2"}}"#), vec!["expected array, found object"]);
+    }
+
+    #[test]
+    fn strict_json_types() {
+        let schema = Schema::new(r#"{"properties": {
+            "zip": {"type": "string", "maxLength": 3},
+            "tags": {"items": {"type": "integer"}, "minItems": 2}
+        }}"#).unwrap();
+        let json_messages = |data: &str| -> Vec<String> {
+            let document = crate::JsonDataExtractor::new(data).unwrap();
+            schema.validate(document.parse_data()).into_iter().map(|violation| violation.to_string()).collect()
+        };
+
+        assert_eq!(json_messages(r#"{"zip": 10021}"#), vec!["$.zip at line 1 column 9: expected string, found integer"]);
+        assert_eq!(json_messages(r#"{"zip": true}"#), vec!["$.zip at line 1 column 9: expected string, found boolean"]);
+        // Items are not checked for something which is not an array.
+        assert_eq!(json_messages(r#"{"tags": {"a": "x"}}"#), Vec::<String>::new());
+        assert_eq!(json_messages(r#"{"tags": [1, "x"]}"#), vec!["$.tags[1] at line 1 column 14: expected integer, found string \"x\""]);
+
+        let document = XmlDataExtractor::new("<a><zip>10021</zip></a>").unwrap();
+        let violations = schema.validate_node(&document.parse_data().root()["a"]);
+        assert_eq!(violations.into_iter().map(|violation| violation.message).collect::<Vec<_>>(), vec![
+            "must be at most 3 characters long",
+        ]);
+    }
+
+    #[test]
+    fn invalid_schema() {
+        match Schema::new(r#"{"properties": {"year": {"type": "date"}}}"#) {
+            Err(DataExtractionError::InvalidSchema { path, .. }) => assert_eq!(path, "$.properties.year.type"),
+            _ => panic!("InvalidSchema error is expected"),
+        }
+        assert!(Schema::new(r#"{"required": "title"}"#).is_err());
+    }
+}
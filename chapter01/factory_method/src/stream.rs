@@ -0,0 +1,503 @@
+//! Streaming extraction for files too large to be read at once.
+//!
+//! A stream reads a file piece by piece and yields the elements of
+//! the top-level JSON array, the child elements of the XML root element
+//! or the CSV records one at a time.
+//! Only the element being yielded is kept in memory,
+//! so memory use depends on the largest element rather than the whole file.
+//!
+//! Each element is the same node as the one in the document made by
+//! `extract_data_from`, with positions in the file.
+//! An element which cannot be parsed is yielded as an error and ends the stream.
+
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+
+use quick_xml::events::{BytesStart, Event};
+use serde_json::Value;
+
+use crate::document::{Document, Node, NodeKind, Position};
+use crate::{node_from_csv_record, DataExtractionError};
+
+/// Iterator over elements of a file, created by `stream_data_from`.
+pub type DataStream = Box<dyn Iterator<Item = Result<Node, DataExtractionError>>>;
+
+/// Name of the element which wraps an XML element to be parsed by itself.
+const XML_WRAPPER: &str = "stream";
+
+/// Reader of a file which knows the line and column of what it reads next.
+struct SourceReader {
+    reader: BufReader<File>,
+    offset: u64,
+    position: Position,
+}
+
+impl SourceReader {
+    fn open(filepath: &str) -> Result<SourceReader, DataExtractionError> {
+        let mut reader = BufReader::new(File::open(filepath)?);
+
+        // Parsers don't count a BOM.
+        if reader.fill_buf()?.starts_with(b"\xef\xbb\xbf") {
+            reader.consume(3);
+        }
+
+        Ok(SourceReader {
+            reader,
+            offset: 0,
+            position: Position { line: 1, column: 1 },
+        })
+    }
+
+    fn peek(&mut self) -> Result<Option<u8>, DataExtractionError> {
+        Ok(self.reader.fill_buf()?.first().copied())
+    }
+
+    fn next(&mut self) -> Result<Option<u8>, DataExtractionError> {
+        let byte = self.peek()?;
+
+        if let Some(byte) = byte {
+            self.reader.consume(1);
+            self.offset += 1;
+            if byte == b'\n' {
+                self.position.line += 1;
+                self.position.column = 1;
+            } else if byte & 0xc0 != 0x80 {
+                // Continuation bytes of UTF-8 are not characters.
+                self.position.column += 1;
+            }
+        }
+        Ok(byte)
+    }
+
+    fn skip_whitespace(&mut self) -> Result<(), DataExtractionError> {
+        while self.peek()?.is_some_and(|byte| byte.is_ascii_whitespace()) {
+            self.next()?;
+        }
+        Ok(())
+    }
+
+    /// Read until the byte offset, keeping what is read in `bytes` if it's given.
+    fn read_to(&mut self, offset: u64, mut bytes: Option<&mut Vec<u8>>) -> Result<(), DataExtractionError> {
+        while self.offset < offset {
+            match self.next()? {
+                Some(byte) => if let Some(bytes) = bytes.as_mut() {
+                    bytes.push(byte);
+                },
+                None => break,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum JsonState {
+    Start,
+    First,
+    Next,
+    Done,
+}
+
+/// Stream of the elements of a top-level JSON array.
+pub(crate) struct JsonStream {
+    source: SourceReader,
+    state: JsonState,
+}
+
+impl JsonStream {
+    pub(crate) fn open(filepath: &str) -> Result<JsonStream, DataExtractionError> {
+        Ok(JsonStream {
+            source: SourceReader::open(filepath)?,
+            state: JsonState::Start,
+        })
+    }
+
+    fn syntax_error(&self, message: &str) -> DataExtractionError {
+        DataExtractionError::JsonSyntax {
+            message: message.to_string(),
+            line: self.source.position.line,
+            column: self.source.position.column,
+        }
+    }
+
+    fn next_element(&mut self) -> Result<Option<Node>, DataExtractionError> {
+        self.source.skip_whitespace()?;
+
+        if self.state == JsonState::Start {
+            if self.source.next()? != Some(b'[') {
+                return Err(self.syntax_error("the top level must be an array to be streamed"));
+            }
+            self.state = JsonState::First;
+            self.source.skip_whitespace()?;
+        }
+
+        match self.source.peek()? {
+            Some(b']') => return Ok(None),
+            Some(b',') if self.state == JsonState::Next => {
+                self.source.next()?;
+                self.source.skip_whitespace()?;
+            },
+            Some(_) if self.state == JsonState::First => {},
+            Some(_) => return Err(self.syntax_error("expected `,` or `]`")),
+            None => return Err(self.syntax_error("EOF while parsing a list")),
+        }
+
+        let start = self.source.position;
+        let bytes = self.read_element()?;
+        if bytes.is_empty() {
+            return Err(self.syntax_error("expected value"));
+        }
+
+        let value: Value = serde_json::from_slice(&bytes).map_err(|e| {
+            // serde_json counts from the start of the element.
+            let column = if e.line() == 1 { start.column + e.column() - 1 } else { e.column() };
+            DataExtractionError::JsonSyntax {
+                message: e.to_string(),
+                line: start.line + e.line() - 1,
+                column,
+            }
+        })?;
+        // serde_json has already checked that the element is UTF-8.
+        let source = String::from_utf8(bytes).unwrap();
+
+        let mut node = Document::from_json_source(value, &source).into_root();
+        node.move_positions(Position { line: 1, column: 1 }, start);
+        self.state = JsonState::Next;
+
+        Ok(Some(node))
+    }
+
+    /// Read the bytes of an element without parsing them.
+    ///
+    /// The element ends at `,`, `]` or whitespace which is not in a string or a container.
+    fn read_element(&mut self) -> Result<Vec<u8>, DataExtractionError> {
+        let mut bytes = Vec::new();
+        let mut depth = 0;
+        let mut in_string = false;
+        let mut escaped = false;
+
+        while let Some(byte) = self.source.peek()? {
+            if !in_string && depth == 0 && !bytes.is_empty()
+                && (byte == b',' || byte == b']' || byte.is_ascii_whitespace()) {
+                break;
+            }
+            self.source.next()?;
+            bytes.push(byte);
+
+            if in_string {
+                if escaped {
+                    escaped = false;
+                } else if byte == b'\\' {
+                    escaped = true;
+                } else if byte == b'"' {
+                    in_string = false;
+                }
+            } else {
+                match byte {
+                    b'"' => in_string = true,
+                    b'{' | b'[' => depth += 1,
+                    b'}' | b']' if depth > 0 => depth -= 1,
+                    _ => {},
+                }
+            }
+        }
+
+        Ok(bytes)
+    }
+}
+
+impl Iterator for JsonStream {
+    type Item = Result<Node, DataExtractionError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.state == JsonState::Done {
+            return None;
+        }
+
+        let element = self.next_element();
+        if !matches!(element, Ok(Some(_))) {
+            self.state = JsonState::Done;
+        }
+        element.transpose()
+    }
+}
+
+/// Stream of the child elements of the XML root element, whatever their names are.
+///
+/// quick-xml finds where each child element is,
+/// and then the child element is read again from the file and parsed by roxmltree,
+/// so it's mapped in the same way as `XmlDataExtractor`.
+pub(crate) struct XmlStream {
+    reader: quick_xml::Reader<BufReader<File>>,
+    source: SourceReader,
+    buffer: Vec<u8>,
+    depth: usize,
+    element_start: u64,
+    /// Start tag which declares the namespaces of the root element.
+    wrapper: Vec<u8>,
+    done: bool,
+}
+
+impl XmlStream {
+    pub(crate) fn open(filepath: &str) -> Result<XmlStream, DataExtractionError> {
+        Ok(XmlStream {
+            reader: quick_xml::Reader::from_reader(BufReader::new(File::open(filepath)?)),
+            source: SourceReader::open(filepath)?,
+            buffer: Vec::new(),
+            depth: 0,
+            element_start: 0,
+            wrapper: Vec::new(),
+            done: false,
+        })
+    }
+
+    fn syntax_error(&mut self, offset: u64, message: &str) -> DataExtractionError {
+        if let Err(e) = self.source.read_to(offset, None) {
+            return e;
+        }
+
+        DataExtractionError::XmlSyntax {
+            message: message.to_string(),
+            line: self.source.position.line,
+            column: self.source.position.column,
+        }
+    }
+
+    fn next_element(&mut self) -> Result<Option<Node>, DataExtractionError> {
+        loop {
+            let start = self.reader.buffer_position();
+            self.buffer.clear();
+
+            let event = match self.reader.read_event_into(&mut self.buffer) {
+                Ok(event) => event,
+                Err(e) => {
+                    let offset = self.reader.error_position();
+                    return Err(self.syntax_error(offset, &e.to_string()));
+                },
+            };
+
+            match event {
+                Event::Start(tag) => {
+                    if self.depth == 0 {
+                        self.wrapper = xml_wrapper(&tag);
+                    } else if self.depth == 1 {
+                        self.element_start = start;
+                    }
+                    self.depth += 1;
+                },
+                Event::Empty(_) if self.depth == 0 => return Ok(None),
+                Event::Empty(_) if self.depth == 1 => {
+                    let end = self.reader.buffer_position();
+                    return self.parse_element(start, end).map(Some);
+                },
+                Event::End(_) => {
+                    self.depth -= 1;
+                    match self.depth {
+                        0 => return Ok(None),
+                        1 => {
+                            let end = self.reader.buffer_position();
+                            return self.parse_element(self.element_start, end).map(Some);
+                        },
+                        _ => {},
+                    }
+                },
+                Event::Eof => {
+                    let offset = self.reader.buffer_position();
+                    let message = if self.wrapper.is_empty() { "no root element" } else { "unexpected end of file" };
+                    return Err(self.syntax_error(offset, message));
+                },
+                _ => {},
+            }
+        }
+    }
+
+    fn parse_element(&mut self, start: u64, end: u64) -> Result<Node, DataExtractionError> {
+        self.source.read_to(start, None)?;
+        let origin = self.source.position;
+
+        // The wrapper is on its own line, so the element starts at line 2.
+        let mut bytes = self.wrapper.clone();
+        bytes.push(b'\n');
+        self.source.read_to(end, Some(&mut bytes))?;
+        bytes.extend_from_slice(format!("</{}>", XML_WRAPPER).as_bytes());
+
+        let text = String::from_utf8(bytes).map_err(|_| DataExtractionError::XmlSyntax {
+            message: "invalid UTF-8".to_string(),
+            line: origin.line,
+            column: origin.column,
+        })?;
+        let document = roxmltree::Document::parse(&text).map_err(|e| {
+            let pos = e.pos();
+            let (row, col) = (pos.row as usize, pos.col as usize);
+            let column = if row <= 2 { origin.column + col - 1 } else { col };
+            DataExtractionError::XmlSyntax {
+                message: e.to_string(),
+                line: origin.line + row.max(2) - 2,
+                column,
+            }
+        })?;
+
+        // {"stream": {"person": {...}}}
+        let mut node = Document::from_xml(&document).into_root()[XML_WRAPPER]
+            .as_map()
+            .and_then(|map| map.values().next())
+            .cloned()
+            .unwrap_or_else(|| Node::new(NodeKind::Null));
+        node.move_positions(Position { line: 2, column: 1 }, origin);
+
+        Ok(node)
+    }
+}
+
+/// Make the start tag of the wrapper with the namespace declarations of the root element.
+fn xml_wrapper(root: &BytesStart) -> Vec<u8> {
+    let mut wrapper = format!("<{}", XML_WRAPPER).into_bytes();
+
+    for attribute in root.attributes().flatten() {
+        if attribute.key.as_namespace_binding().is_some() {
+            // The value is still escaped, so only the quote has to be chosen.
+            let quote = if attribute.value.contains(&b'"') { b'\'' } else { b'"' };
+            wrapper.push(b' ');
+            wrapper.extend_from_slice(attribute.key.as_ref());
+            wrapper.push(b'=');
+            wrapper.push(quote);
+            wrapper.extend(attribute.value.iter().map(|&byte| if byte == b'\n' { b' ' } else { byte }));
+            wrapper.push(quote);
+        }
+    }
+    wrapper.push(b'>');
+
+    wrapper
+}
+
+impl Iterator for XmlStream {
+    type Item = Result<Node, DataExtractionError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let element = self.next_element();
+        if !matches!(element, Ok(Some(_))) {
+            self.done = true;
+        }
+        element.transpose()
+    }
+}
+
+/// Stream of CSV records.
+pub(crate) struct CsvStream {
+    headers: csv::StringRecord,
+    records: csv::StringRecordsIntoIter<File>,
+    done: bool,
+}
+
+impl CsvStream {
+    pub(crate) fn open(filepath: &str) -> Result<CsvStream, DataExtractionError> {
+        let mut reader = csv::Reader::from_reader(File::open(filepath)?);
+        let headers = reader.headers()?.clone();
+
+        Ok(CsvStream {
+            headers,
+            records: reader.into_records(),
+            done: false,
+        })
+    }
+}
+
+impl Iterator for CsvStream {
+    type Item = Result<Node, DataExtractionError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        match self.records.next()? {
+            Ok(record) => Some(Ok(node_from_csv_record(&self.headers, &record))),
+            Err(e) => {
+                self.done = true;
+                Some(Err(e.into()))
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::tests::temp_file;
+    use crate::{extract_data_from, stream_data_from};
+
+    fn assert_same_as_document(filepath: &str, elements: &Node) {
+        let streamed: Vec<Node> = stream_data_from(filepath.to_string())
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+
+        assert_eq!(streamed.iter().collect::<Vec<_>>(), elements.members());
+        for (streamed, element) in streamed.iter().zip(elements.members()) {
+            assert_eq!(streamed.position(), element.position());
+        }
+    }
+
+    #[test]
+    fn same_as_document() {
+        let movies = extract_data_from("data/movies.json".to_string()).unwrap();
+        assert_same_as_document("data/movies.json", movies.parsed_data().root());
+
+        let persons = extract_data_from("data/person.xml".to_string()).unwrap();
+        assert_same_as_document("data/person.xml", &persons.parsed_data().root()["persons"]["person"]);
+        let patty = stream_data_from("data/person.xml".to_string()).unwrap().nth(2).unwrap().unwrap();
+        assert_eq!(patty["phoneNumbers"]["phoneNumber"][1].position(), Some(Position { line: 49, column: 7 }));
+
+        let movies = extract_data_from("data/movies.csv".to_string()).unwrap();
+        assert_same_as_document("data/movies.csv", movies.parsed_data().root());
+    }
+
+    #[test]
+    fn json_stream_errors() {
+        let filepath = temp_file("factory_method_stream_error.json", "[{\"a\": 1},\n {\"a\": }]");
+        let mut stream = stream_data_from(filepath).unwrap();
+
+        assert_eq!(stream.next().unwrap().unwrap()["a"].as_i64(), Some(1));
+        match stream.next() {
+            Some(Err(DataExtractionError::JsonSyntax { line, column, .. })) => assert_eq!((line, column), (2, 8)),
+            _ => panic!("JsonSyntax error is expected"),
+        }
+        assert!(stream.next().is_none());
+
+        let filepath = temp_file("factory_method_stream_object.json", "{\"a\": [1, 2]}");
+        assert!(matches!(
+            stream_data_from(filepath).unwrap().next(),
+            Some(Err(DataExtractionError::JsonSyntax { line: 1, column: 2, .. }))
+        ));
+
+        let filepath = temp_file("factory_method_stream_strings.json", "[\"a,]\\\"\", [], \"\"]");
+        let strings: Vec<Node> = stream_data_from(filepath).unwrap().collect::<Result<_, _>>().unwrap();
+        assert_eq!(strings.len(), 3);
+        assert_eq!(strings[0].as_str(), Some("a,]\""));
+    }
+
+    #[test]
+    fn xml_stream_namespaces_and_errors() {
+        let filepath = temp_file(
+            "factory_method_stream_ns.xml",
+            "<a:persons xmlns:a=\"urn:a\"><a:person id=\"1\"><a:name>A</a:name></a:person>text<a:person/></a:persons>",
+        );
+        let persons: Vec<Node> = stream_data_from(filepath).unwrap().collect::<Result<_, _>>().unwrap();
+        assert_eq!(persons.len(), 2);
+        assert_eq!(persons[0]["name"].as_str(), Some("A"));
+        assert_eq!(persons[0].attribute("id"), Some("1"));
+        assert_eq!(persons[0].position(), Some(Position { line: 1, column: 28 }));
+        assert!(persons[1].is_null());
+
+        let filepath = temp_file("factory_method_stream_error.xml", "<persons>\n  <person></persn>\n</persons>");
+        match stream_data_from(filepath).unwrap().next() {
+            Some(Err(DataExtractionError::XmlSyntax { line, .. })) => assert_eq!(line, 2),
+            _ => panic!("XmlSyntax error is expected"),
+        }
+    }
+}
@@ -0,0 +1,479 @@
+//! Writers which are the counterparts of the extractors.
+//!
+//! A writer turns a `Document` into text of its format,
+//! so data can be converted between any formats the factory supports.
+//! Some documents have no natural shape in a format:
+//!
+//! * XML: The root needs a single entry to be the root element,
+//!   otherwise everything is put in a `<root>` element.
+//!   Elements of a sequence are repeated elements named after their key,
+//!   or `<item>` when there is no key.
+//!   Attributes and `#text` are written back as they were read.
+//! * CSV: Records are found in the same way as `Vec<T>` is deserialized,
+//!   so person.xml gives one record per person.
+//!   Nested data is flattened into columns like `address.city` or
+//!   `phoneNumbers.phoneNumber[1].@type`, and is not restored when it's read again.
+//!   An entry which is a sequence in any record is indexed in every record,
+//!   so a single `<phoneNumber>` is `phoneNumber[0]` too.
+//!   Columns are in the order they are found in the records.
+//! * TOML: The root must be a map. A sequence is put under `items`,
+//!   the same way as XML puts it in `<root>`. Null entries are left out since TOML has no null.
+//!
+//! XML and CSV have no types, so JSON and TOML writers write their text
+//! back as strings, and `<postalCode>10021</postalCode>` stays `"10021"`.
+
+use std::collections::{BTreeMap, HashSet};
+
+use crate::document::{Document, Map, Node, NodeKind, ATTRIBUTE_PREFIX, TEXT_KEY};
+use crate::{DataExtractionError, DataFormat};
+
+/// Common interface of writers created by `data_writer_for`.
+pub trait DataWriter {
+    fn format(&self) -> DataFormat;
+    fn write(&self, document: &Document) -> Result<String, DataExtractionError>;
+}
+
+pub struct JsonDataWriter {}
+
+pub struct XmlDataWriter {}
+
+pub struct CsvDataWriter {}
+
+pub struct TomlDataWriter {}
+
+/// Create a writer for the format.
+pub fn data_writer_for(format: DataFormat) -> Box<dyn DataWriter> {
+    match format {
+        DataFormat::Json => Box::new(JsonDataWriter {}),
+        DataFormat::Xml => Box::new(XmlDataWriter {}),
+        DataFormat::Csv => Box::new(CsvDataWriter {}),
+        DataFormat::Toml => Box::new(TomlDataWriter {}),
+    }
+}
+
+fn unrepresentable(format: DataFormat, message: &str) -> DataExtractionError {
+    DataExtractionError::Unrepresentable {
+        format,
+        message: message.to_string(),
+    }
+}
+
+impl DataWriter for JsonDataWriter {
+    fn format(&self) -> DataFormat {
+        DataFormat::Json
+    }
+
+    fn write(&self, document: &Document) -> Result<String, DataExtractionError> {
+        // Serializing a Value never fails.
+        Ok(serde_json::to_string_pretty(&document.to_json_as_written()).unwrap() + "\n")
+    }
+}
+
+impl DataWriter for XmlDataWriter {
+    fn format(&self) -> DataFormat {
+        DataFormat::Xml
+    }
+
+    fn write(&self, document: &Document) -> Result<String, DataExtractionError> {
+        let mut xml = String::new();
+        let root = document.root();
+
+        // There must be exactly one root element.
+        let single = root.as_map().filter(|map| map.len() == 1 && root.attributes().is_empty());
+        let items;
+        let (name, node) = match single.and_then(|map| map.iter().next()) {
+            Some((name, node)) if node.as_sequence().is_none() => (name.as_str(), node),
+            _ if root.as_sequence().is_some() => {
+                let mut map = Map::new();
+                map.insert("item".to_string(), root.clone());
+                items = Node::new(NodeKind::Map(map));
+                ("root", &items)
+            },
+            _ => ("root", root),
+        };
+        write_xml_element(&mut xml, name, node, 0)?;
+
+        Ok(xml)
+    }
+}
+
+fn write_xml_element(xml: &mut String, name: &str, node: &Node, depth: usize) -> Result<(), DataExtractionError> {
+    if !is_xml_name(name) {
+        return Err(unrepresentable(DataFormat::Xml, &format!("'{}' cannot be an element name", name)));
+    }
+
+    // A sequence is repeated elements with the same name.
+    if let Some(sequence) = node.as_sequence() {
+        for element in sequence {
+            if element.as_sequence().is_some() {
+                // Elements of a nested sequence have no name of their own.
+                write_xml_open_tag(xml, name, element, depth)?;
+                xml.push('\n');
+                write_xml_element(xml, "item", element, depth + 1)?;
+                xml.push_str(&format!("{}</{}>\n", "  ".repeat(depth), name));
+            } else {
+                write_xml_element(xml, name, element, depth)?;
+            }
+        }
+        return Ok(());
+    }
+
+    write_xml_open_tag(xml, name, node, depth)?;
+
+    match node.kind() {
+        NodeKind::Null => {
+            xml.pop();
+            xml.push_str("/>\n");
+        },
+        NodeKind::Map(map) => {
+            xml.push('\n');
+            if let Some(text) = map.get(TEXT_KEY) {
+                xml.push_str(&format!("{}{}\n", "  ".repeat(depth + 1), escape_xml(&text.to_string())));
+            }
+            for (key, child) in map.iter().filter(|(key, _)| key.as_str() != TEXT_KEY) {
+                write_xml_element(xml, key, child, depth + 1)?;
+            }
+            xml.push_str(&format!("{}</{}>\n", "  ".repeat(depth), name));
+        },
+        _ => {
+            xml.push_str(&format!("{}</{}>\n", escape_xml(&node.to_string()), name));
+        },
+    }
+
+    Ok(())
+}
+
+fn write_xml_open_tag(xml: &mut String, name: &str, node: &Node, depth: usize) -> Result<(), DataExtractionError> {
+    xml.push_str(&format!("{}<{}", "  ".repeat(depth), name));
+
+    for (attribute, value) in node.attributes() {
+        if !is_xml_name(attribute) {
+            return Err(unrepresentable(DataFormat::Xml, &format!("'{}' cannot be an attribute name", attribute)));
+        }
+        xml.push_str(&format!(" {}=\"{}\"", attribute, escape_xml(value)));
+    }
+    xml.push('>');
+
+    Ok(())
+}
+
+fn is_xml_name(name: &str) -> bool {
+    let mut chars = name.chars();
+
+    match chars.next() {
+        Some(c) if c.is_alphabetic() || c == '_' => {},
+        _ => return false,
+    }
+    chars.all(|c| c.is_alphanumeric() || c == '_' || c == '-' || c == '.')
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+impl DataWriter for CsvDataWriter {
+    fn format(&self) -> DataFormat {
+        DataFormat::Csv
+    }
+
+    fn write(&self, document: &Document) -> Result<String, DataExtractionError> {
+        let records = csv_records(document.root());
+
+        let mut sequences = HashSet::new();
+        for record in &records {
+            find_sequences(record, String::new(), &mut sequences);
+        }
+
+        let records: Vec<Vec<(String, String)>> = records.into_iter()
+            .map(|record| {
+                let mut columns = Vec::new();
+                flatten(record, String::new(), String::new(), &sequences, &mut columns);
+                columns
+            })
+            .collect();
+
+        if records.is_empty() {
+            return Err(unrepresentable(DataFormat::Csv, "there are no records"));
+        }
+
+        // A column first found in a later record is put after
+        // the column before it in that record, to keep the order of the source.
+        let mut header: Vec<String> = Vec::new();
+        for record in &records {
+            let mut next = 0;
+            for (column, _) in record {
+                match header.iter().position(|found| found == column) {
+                    Some(i) => next = i + 1,
+                    None => {
+                        header.insert(next, column.clone());
+                        next += 1;
+                    },
+                }
+            }
+        }
+
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        let unwritable = |e: csv::Error| unrepresentable(DataFormat::Csv, &e.to_string());
+
+        writer.write_record(&header).map_err(unwritable)?;
+        for record in records {
+            let fields: BTreeMap<String, String> = record.into_iter().collect();
+            let row = header.iter().map(|column| fields.get(column).map(|field| field.as_str()).unwrap_or(""));
+            writer.write_record(row).map_err(unwritable)?;
+        }
+
+        let data = writer.into_inner().map_err(|e| unrepresentable(DataFormat::Csv, &e.to_string()))?;
+        Ok(String::from_utf8(data).unwrap())
+    }
+}
+
+/// Find records in the same way as `Vec<T>` is deserialized.
+fn csv_records(node: &Node) -> Vec<&Node> {
+    if let Some(map) = node.as_map() {
+        if map.len() == 1 && node.attributes().is_empty() {
+            let entry = map.values().next().unwrap();
+            if entry.as_map().is_some() || entry.as_sequence().is_some() || !entry.attributes().is_empty() {
+                return csv_records(entry);
+            }
+        }
+    }
+
+    node.members()
+}
+
+fn join_column(prefix: &str, key: &str) -> String {
+    if prefix.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", prefix, key)
+    }
+}
+
+/// Collect columns which are sequences, with `[]` in place of indexes.
+fn find_sequences(node: &Node, shape: String, sequences: &mut HashSet<String>) {
+    match node.kind() {
+        NodeKind::Map(map) => {
+            for (key, child) in map {
+                find_sequences(child, join_column(&shape, key), sequences);
+            }
+        },
+        NodeKind::Sequence(sequence) => {
+            for child in sequence {
+                find_sequences(child, format!("{}[]", shape), sequences);
+            }
+            sequences.insert(shape);
+        },
+        _ => {},
+    }
+}
+
+/// Flatten a node into columns named in the query syntax without `$`.
+///
+/// `shape` is the column without indexes, which is looked up in `sequences`.
+fn flatten(node: &Node, prefix: String, shape: String, sequences: &HashSet<String>, columns: &mut Vec<(String, String)>) {
+    if node.as_sequence().is_none() && sequences.contains(&shape) {
+        // A single element is the first element of a sequence in other records.
+        if !node.is_null() {
+            flatten(node, format!("{}[0]", prefix), format!("{}[]", shape), sequences, columns);
+        }
+        return;
+    }
+
+    let join = |key: &str| join_column(&prefix, key);
+
+    for (name, value) in node.attributes() {
+        columns.push((join(&format!("{}{}", ATTRIBUTE_PREFIX, name)), value.clone()));
+    }
+
+    match node.kind() {
+        NodeKind::Map(map) => {
+            for (key, child) in map {
+                flatten(child, join(key), join_column(&shape, key), sequences, columns);
+            }
+        },
+        NodeKind::Sequence(sequence) => {
+            for (i, child) in sequence.iter().enumerate() {
+                flatten(child, format!("{}[{}]", prefix, i), format!("{}[]", shape), sequences, columns);
+            }
+        },
+        NodeKind::Null => columns.push((prefix, String::new())),
+        _ if node.attributes().is_empty() => columns.push((prefix, node.to_string())),
+        _ => columns.push((join(TEXT_KEY), node.to_string())),
+    }
+}
+
+impl DataWriter for TomlDataWriter {
+    fn format(&self) -> DataFormat {
+        DataFormat::Toml
+    }
+
+    fn write(&self, document: &Document) -> Result<String, DataExtractionError> {
+        let root = document.root();
+        let items;
+        let root = match root.kind() {
+            NodeKind::Map(_) => root,
+            NodeKind::Sequence(_) => {
+                let mut map = Map::new();
+                map.insert("items".to_string(), root.clone());
+                items = Node::new(NodeKind::Map(map));
+                &items
+            },
+            _ => return Err(unrepresentable(DataFormat::Toml, "the root must be a map or a sequence")),
+        };
+
+        let value = node_to_toml(root)?.unwrap();
+        toml::to_string_pretty(&value).map_err(|e| unrepresentable(DataFormat::Toml, &e.to_string()))
+    }
+}
+
+/// Convert a node to a TOML value. Null is `None`.
+fn node_to_toml(node: &Node) -> Result<Option<toml::Value>, DataExtractionError> {
+    let value = match node.kind() {
+        NodeKind::Null => None,
+        NodeKind::Bool(_) | NodeKind::Integer(_) | NodeKind::Float(_) if node.is_untyped() => {
+            Some(toml::Value::String(node.to_string()))
+        },
+        NodeKind::Bool(boolean) => Some(toml::Value::Boolean(*boolean)),
+        NodeKind::Integer(integer) => Some(toml::Value::Integer(*integer)),
+        NodeKind::Float(float) => Some(toml::Value::Float(*float)),
+        NodeKind::String(string) => Some(toml::Value::String(string.clone())),
+        NodeKind::Sequence(sequence) => {
+            let mut array = Vec::new();
+            for element in sequence {
+                match node_to_toml(element)? {
+                    Some(value) => array.push(value),
+                    None => return Err(unrepresentable(DataFormat::Toml, "an array cannot have null")),
+                }
+            }
+            Some(toml::Value::Array(array))
+        },
+        NodeKind::Map(map) => {
+            let mut table = toml::value::Table::new();
+            for (key, child) in map {
+                if let Some(value) = node_to_toml(child)? {
+                    table.insert(key.clone(), value);
+                }
+            }
+            Some(toml::Value::Table(table))
+        },
+    };
+
+    if node.attributes().is_empty() {
+        return Ok(value);
+    }
+
+    let mut table = match value {
+        Some(toml::Value::Table(table)) => table,
+        Some(value) => {
+            let mut table = toml::value::Table::new();
+            table.insert(TEXT_KEY.to_string(), value);
+            table
+        },
+        None => toml::value::Table::new(),
+    };
+    for (name, attribute) in node.attributes() {
+        table.insert(format!("{}{}", ATTRIBUTE_PREFIX, name), toml::Value::String(attribute.clone()));
+    }
+
+    Ok(Some(toml::Value::Table(table)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{CsvDataExtractor, JsonDataExtractor, TomlDataExtractor, XmlDataExtractor};
+
+    fn xml_document(data: &str) -> Document {
+        XmlDataExtractor::new(data).unwrap().parse_data().clone()
+    }
+
+    #[test]
+    fn xml_round_trip() {
+        let document = xml_document(
+            r#"<persons><person id="1"><name>A &amp; B</name><phone type="home">1</phone><phone type="fax">2</phone><note/></person></persons>"#
+        );
+        let xml = XmlDataWriter {}.write(&document).unwrap();
+
+        assert_eq!(xml_document(&xml), document);
+        assert!(xml.contains("<phone type=\"fax\">2</phone>"));
+    }
+
+    #[test]
+    fn xml_root_and_names() {
+        let document = JsonDataExtractor::new(r#"[{"title": "A"}, {"title": "B"}]"#).unwrap().parse_data().clone();
+        let xml = XmlDataWriter {}.write(&document).unwrap();
+        assert!(xml.starts_with("<root>\n  <item>\n    <title>A</title>\n  </item>\n  <item>\n"));
+        assert_eq!(xml_document(&xml).root()["root"]["item"][1]["title"].as_str(), Some("B"));
+
+        let document = JsonDataExtractor::new(r#"{"a": {"first name": "A"}}"#).unwrap().parse_data().clone();
+        match (XmlDataWriter {}).write(&document) {
+            Err(DataExtractionError::Unrepresentable { format, .. }) => assert_eq!(format, DataFormat::Xml),
+            _ => panic!("Unrepresentable error is expected"),
+        }
+    }
+
+    #[test]
+    fn csv_flattened() {
+        let document = xml_document(
+            r#"<persons><person><name>A</name><address><city>X</city></address><phone type="home">1</phone></person><person><name>B, C</name><age>3</age></person></persons>"#
+        );
+        let csv = CsvDataWriter {}.write(&document).unwrap();
+
+        assert_eq!(csv, "name,age,address.city,phone.@type,phone.#text\nA,,X,home,1\n\"B, C\",3,,,\n");
+    }
+
+    #[test]
+    fn csv_single_and_repeated_children() {
+        let document = xml_document(
+            r#"<persons><person><phone type="home">1</phone></person><person><phone type="fax">2</phone><phone type="mobile">3</phone></person></persons>"#
+        );
+        let csv = CsvDataWriter {}.write(&document).unwrap();
+
+        assert_eq!(csv, "phone[0].@type,phone[0].#text,phone[1].@type,phone[1].#text\nhome,1,,\nfax,2,mobile,3\n");
+    }
+
+    #[test]
+    fn csv_round_trip() {
+        let csv = "title,year,director\nCaught,1900,\n";
+        let document = CsvDataExtractor::new(csv).unwrap().parse_data().clone();
+        let written = CsvDataWriter {}.write(&document).unwrap();
+
+        assert_eq!(CsvDataExtractor::new(&written).unwrap().parse_data(), &document);
+    }
+
+    #[test]
+    fn toml_output() {
+        let document = xml_document(r#"<movie><title>Caught</title><year>1900</year><director/></movie>"#);
+        let toml = TomlDataWriter {}.write(&document).unwrap();
+
+        let read = TomlDataExtractor::new(&toml).unwrap().parse_data().clone();
+        assert_eq!(read.root()["movie"]["title"].as_str(), Some("Caught"));
+        assert_eq!(read.root()["movie"]["year"].as_str(), Some("1900"));
+        assert!(read.root()["movie"].get("director").is_none());
+
+        let document = JsonDataExtractor::new(r#"[{"year": 1900}, {"year": 1901}]"#).unwrap().parse_data().clone();
+        let toml = TomlDataWriter {}.write(&document).unwrap();
+        let read = TomlDataExtractor::new(&toml).unwrap().parse_data().clone();
+        assert_eq!(read.root()["items"][1]["year"].as_i64(), Some(1901));
+
+        let document = JsonDataExtractor::new("1").unwrap().parse_data().clone();
+        assert!(TomlDataWriter {}.write(&document).is_err());
+    }
+
+    #[test]
+    fn json_keeps_xml_text() {
+        let document = xml_document(r#"<address><postalCode>10021</postalCode><note type="1">2</note></address>"#);
+        let json = JsonDataWriter {}.write(&document).unwrap();
+        let read = JsonDataExtractor::new(&json).unwrap().parse_data().clone();
+
+        assert_eq!(read.root()["address"]["postalCode"].as_str(), Some("10021"));
+        assert_eq!(read.root()["address"]["note"].as_str(), Some("2"));
+
+        let document = JsonDataExtractor::new(r#"{"postalCode": 10021}"#).unwrap().parse_data().clone();
+        assert!(JsonDataWriter {}.write(&document).unwrap().contains("\"postalCode\": 10021"));
+    }
+}
@@ -1,45 +1,240 @@
-// const MINI14: String = String::from("1.4GHz Mac mini");
-const MINI14: &str = "1.4GHz Mac mini";
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+
+use serde::Deserialize;
+
+/// Path of the catalog which lists the models and their specs.
+const CATALOG: &str = "data/catalog.toml";
 
 trait Computer {
     fn get_info(&self) -> String;
 }
 
-struct AppleFactory {}
-
-struct MacMini14 {
+/// A model in the catalog.
+#[derive(Debug, Clone, Deserialize)]
+struct ModelSpec {
+    /// Code to order the model with, like "MINI14".
+    code: String,
+    /// Name of the model, like "1.4GHz Mac mini".
+    name: String,
     memory: u32,
     hdd: u32,
     gpu: String,
 }
 
-impl AppleFactory {
-    fn build_computer(model: &str) -> Option<Box<Computer>> {
-        // https://stackoverflow.com/questions/25383488/how-to-match-a-string-against-string-literals-in-rust
-        match model {
-            "MINI14" => Some(Box::new(MacMini14 {
-                memory: 4,
-                hdd: 500,
-                gpu: String::from("Intel HD Graphics 5000"),
-                })),
+#[derive(Debug, Deserialize)]
+struct Catalog {
+    models: Vec<ModelSpec>,
+}
+
+#[derive(Debug)]
+enum FactoryError {
+    Io(io::Error),
+    InvalidCatalog(String),
+    ModelNotFound { model: String, suggestions: Vec<String> },
+}
+
+impl Error for FactoryError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            FactoryError::Io(e) => Some(e),
             _ => None,
         }
     }
 }
 
-impl Computer for MacMini14 {
+impl fmt::Display for FactoryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FactoryError::Io(e) => write!(f, "Cannot read the catalog: {}", e),
+            FactoryError::InvalidCatalog(message) => write!(f, "Invalid catalog: {}", message),
+            FactoryError::ModelNotFound { model, suggestions } => {
+                write!(f, "Model not found: {}", model)?;
+                if !suggestions.is_empty() {
+                    write!(f, ". Did you mean {}?", suggestions.join(" or "))?;
+                }
+                Ok(())
+            },
+        }
+    }
+}
+
+impl From<io::Error> for FactoryError {
+    fn from(e: io::Error) -> Self {
+        FactoryError::Io(e)
+    }
+}
+
+impl From<toml::de::Error> for FactoryError {
+    fn from(e: toml::de::Error) -> Self {
+        FactoryError::InvalidCatalog(e.to_string())
+    }
+}
+
+struct AppleFactory {
+    catalog: Catalog,
+}
+
+struct MacComputer {
+    spec: ModelSpec,
+}
+
+impl AppleFactory {
+    fn from_catalog(filepath: &str) -> Result<AppleFactory, FactoryError> {
+        let catalog: Catalog = toml::from_str(&fs::read_to_string(filepath)?)?;
+
+        for (i, spec) in catalog.models.iter().enumerate() {
+            if catalog.models[..i].iter().any(|other| other.code == spec.code) {
+                return Err(FactoryError::InvalidCatalog(format!("{} is listed twice", spec.code)));
+            }
+        }
+
+        Ok(AppleFactory { catalog })
+    }
+
+    /// Return the codes of the models in the order of the catalog.
+    fn list_models(&self) -> Vec<&str> {
+        self.catalog.models.iter().map(|spec| spec.code.as_str()).collect()
+    }
+
+    fn build_computer(&self, model: &str) -> Result<Box<dyn Computer>, FactoryError> {
+        match self.catalog.models.iter().find(|spec| spec.code == model) {
+            Some(spec) => Ok(Box::new(MacComputer { spec: spec.clone() })),
+            None => Err(FactoryError::ModelNotFound {
+                model: String::from(model),
+                suggestions: self.similar_models(model),
+            }),
+        }
+    }
+
+    /// Find models whose codes look like the given one.
+    ///
+    /// They are the closest codes within two typos, and codes which contain
+    /// the given one like "MINI".
+    fn similar_models(&self, model: &str) -> Vec<String> {
+        let model = model.to_uppercase();
+        let distances: Vec<(usize, &str)> = self.list_models()
+            .into_iter()
+            .map(|code| (strsim::damerau_levenshtein(&model, &code.to_uppercase()), code))
+            .collect();
+        let closest = distances.iter().map(|&(distance, _)| distance).min().filter(|&distance| distance <= 2);
+
+        distances.into_iter()
+            .filter(|&(distance, code)| {
+                Some(distance) == closest || (model.len() >= 3 && code.to_uppercase().contains(&model))
+            })
+            .map(|(_, code)| String::from(code))
+            .collect()
+    }
+}
+
+impl Computer for MacComputer {
     fn get_info(&self) -> String {
         format!("Model: {}\nMemory: {}\nHard Disk: {}\nGraphics Card: {}",
-            MINI14,
-            &self.memory,
-            &self.hdd,
-            &self.gpu
+            &self.spec.name,
+            &self.spec.memory,
+            &self.spec.hdd,
+            &self.spec.gpu
         )
     }
 }
 
 
 fn main() {
-    let mac_mini = AppleFactory::build_computer("MINI14").unwrap();
-    println!("{}", mac_mini.get_info());
+    let factory = match AppleFactory::from_catalog(CATALOG) {
+        Ok(factory) => factory,
+        Err(e) => {
+            eprintln!("{}", e);
+            return;
+        },
+    };
+    println!("Models: {}\n", factory.list_models().join(", "));
+
+    let mac_mini = factory.build_computer("MINI14").unwrap();
+    println!("{}\n", mac_mini.get_info());
+
+    if let Err(e) = factory.build_computer("MINI41") {
+        println!("{}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::env;
+    use std::process;
+
+    fn factory() -> AppleFactory {
+        AppleFactory::from_catalog(CATALOG).unwrap()
+    }
+
+    /// Write a catalog to a file unique to the test and the run.
+    fn catalog_file(name: &str, data: &str) -> String {
+        let filepath = env::temp_dir().join(format!("apple_factory_{}_{}.toml", process::id(), name));
+        fs::write(&filepath, data).unwrap();
+        filepath.to_str().unwrap().to_string()
+    }
+
+    fn suggestions(model: &str) -> Vec<String> {
+        match factory().build_computer(model) {
+            Err(FactoryError::ModelNotFound { suggestions, .. }) => suggestions,
+            Err(e) => panic!("ModelNotFound error is expected: {}", e),
+            Ok(_) => panic!("ModelNotFound error is expected"),
+        }
+    }
+
+    #[test]
+    fn load_catalog() {
+        let factory = factory();
+
+        assert_eq!(factory.list_models(), vec!["MINI14", "MINI26", "MINI28", "IMAC27"]);
+        assert!(factory.build_computer("IMAC27").unwrap().get_info().starts_with("Model: 27-inch iMac\n"));
+    }
+
+    #[test]
+    fn invalid_catalogs() {
+        let model = "[[models]]\ncode = \"A\"\nname = \"A\"\nmemory = 4\nhdd = 500\ngpu = \"G\"\n";
+
+        let filepath = catalog_file("duplicate", &model.repeat(2));
+        match AppleFactory::from_catalog(&filepath) {
+            Err(FactoryError::InvalidCatalog(message)) => assert_eq!(message, "A is listed twice"),
+            _ => panic!("InvalidCatalog error is expected"),
+        }
+
+        let filepath = catalog_file("missing_field", &model.replace("hdd = 500\n", ""));
+        match AppleFactory::from_catalog(&filepath) {
+            Err(FactoryError::InvalidCatalog(message)) => assert!(message.contains("hdd")),
+            _ => panic!("InvalidCatalog error is expected"),
+        }
+
+        assert!(matches!(AppleFactory::from_catalog("data/missing.toml"), Err(FactoryError::Io(_))));
+    }
+
+    #[test]
+    fn exact_model() {
+        let mac_mini = factory().build_computer("MINI14").unwrap();
+
+        assert_eq!(mac_mini.get_info(), "Model: 1.4GHz Mac mini\nMemory: 4\nHard Disk: 500\nGraphics Card: Intel HD Graphics 5000");
+    }
+
+    #[test]
+    fn similar_models() {
+        assert_eq!(suggestions("MINI41"), vec!["MINI14"]);
+        assert_eq!(suggestions("mini14"), vec!["MINI14"]);
+        assert_eq!(suggestions("IMAC"), vec!["IMAC27"]);
+        assert_eq!(suggestions("MINI"), vec!["MINI14", "MINI26", "MINI28"]);
+        assert_eq!(
+            factory().build_computer("MINI41").err().unwrap().to_string(),
+            "Model not found: MINI41. Did you mean MINI14?"
+        );
+    }
+
+    #[test]
+    fn no_similar_model() {
+        assert_eq!(suggestions("XSERVE"), Vec::<String>::new());
+        assert_eq!(factory().build_computer("XSERVE").err().unwrap().to_string(), "Model not found: XSERVE");
+    }
 }
@@ -1,80 +1,605 @@
+use std::fmt;
+
+use serde::{Serialize, Serializer};
+
 struct Computer {
     serial: String,
-    memory: Option<u32>,
-    hdd: Option<u32>,
+    memory: u32,
+    hdd: u32,
+    /// Integrated graphics are used if it's not set.
     gpu: Option<String>,
 }
 
 impl Computer {
     fn get_info(&self) -> String {
-        let info = format!("Memory: {}GB\nHard Disk: {}GB\nGraphics Card: {}",
-            self.memory.as_ref().unwrap(),
-            self.hdd.as_ref().unwrap(),
-            self.gpu.as_ref().unwrap()
-        );
-        info
+        format!("Serial Number: {}\nMemory: {}GB\nHard Disk: {}GB\nGraphics Card: {}",
+            self.serial,
+            self.memory,
+            self.hdd,
+            self.gpu.as_deref().unwrap_or("Integrated")
+        )
+    }
+
+    /// List the parts with their prices. Integrated graphics are not listed.
+    fn bill_of_materials(&self) -> Result<BillOfMaterials, ConfigurationError> {
+        let mut items = vec![
+            LineItem {
+                part: String::from("Memory"),
+                description: format!("{}GB", self.memory),
+                price: Price(u64::from(self.memory) * MEMORY_PRICE_PER_GB),
+            },
+            LineItem {
+                part: String::from("Hard Disk"),
+                description: format!("{}GB", self.hdd),
+                price: Price(u64::from(self.hdd) * HDD_PRICE_PER_GB),
+            },
+        ];
+        if let Some(gpu) = &self.gpu {
+            let model = find_gpu(gpu).ok_or_else(|| ConfigurationError::UnknownGpu(gpu.clone()))?;
+            items.push(LineItem {
+                part: String::from("Graphics Card"),
+                description: gpu.clone(),
+                price: Price(model.price),
+            });
+        }
+
+        let total = Price(items.iter().map(|item| item.price.0).sum());
+        Ok(BillOfMaterials {
+            serial: self.serial.clone(),
+            items,
+            total,
+        })
+    }
+}
+
+/// Amount in cents.
+///
+/// Prices of any sizes in u32 fit in u64, even for a computer which is not validated.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Price(u64);
+
+impl fmt::Display for Price {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{:02}", self.0 / 100, self.0 % 100)
+    }
+}
+
+// Exported as a decimal string such as "12.50" so that no precision is lost.
+impl Serialize for Price {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct LineItem {
+    part: String,
+    description: String,
+    price: Price,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct BillOfMaterials {
+    serial: String,
+    items: Vec<LineItem>,
+    total: Price,
+}
+
+impl BillOfMaterials {
+    fn to_json(&self) -> String {
+        // Serializing strings never fails.
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    /// One row per part followed by a row of the total.
+    fn to_csv(&self) -> String {
+        let mut writer = csv::Writer::from_writer(Vec::new());
+
+        // Writing to a Vec never fails.
+        writer.write_record(["serial", "part", "description", "price"]).unwrap();
+        for item in &self.items {
+            let price = item.price.to_string();
+            writer.write_record([&self.serial, &item.part, &item.description, &price]).unwrap();
+        }
+        writer.write_record([self.serial.as_str(), "Total", "", &self.total.to_string()]).unwrap();
+
+        String::from_utf8(writer.into_inner().unwrap()).unwrap()
+    }
+}
+
+impl fmt::Display for BillOfMaterials {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Bill of Materials for {}", self.serial)?;
+        for item in &self.items {
+            writeln!(f, "  {:<14}{:<20}{:>10}", item.part, item.description, item.price)?;
+        }
+        write!(f, "  {:<34}{:>10}", "Total", self.total)
+    }
+}
+
+/// The last number which fits in the 8 digits of a serial number.
+const MAX_SERIAL: u32 = 99_999_999;
+
+/// Serial numbers are the prefix followed by 8 digits counted up from the start.
+struct SerialNumberGenerator {
+    prefix: String,
+    next: u32,
+}
+
+impl SerialNumberGenerator {
+    fn new(prefix: &str, start: u32) -> SerialNumberGenerator {
+        SerialNumberGenerator {
+            prefix: String::from(prefix),
+            next: start,
+        }
+    }
+
+    /// `None` when all the numbers up to `MAX_SERIAL` are used.
+    fn generate(&mut self) -> Option<String> {
+        if self.next > MAX_SERIAL {
+            return None;
+        }
+
+        let serial = format!("{}{:08}", self.prefix, self.next);
+        self.next = self.next.checked_add(1)?;
+        Some(serial)
     }
 }
 
-struct ComputerBuilder {
-    computer: Computer,
+// States of the required parts of ComputerBuilder.
+struct Missing;
+struct Configured(u32);
+
+/// `build()` is available only after the memory and the hard disk are configured.
+/// The graphics card is optional.
+struct ComputerBuilder<Memory, Hdd> {
+    serial: String,
+    memory: Memory,
+    hdd: Hdd,
+    gpu: Option<String>,
 }
 
-impl ComputerBuilder {
-    fn new() -> ComputerBuilder {
+impl ComputerBuilder<Missing, Missing> {
+    fn new(serial: String) -> ComputerBuilder<Missing, Missing> {
         ComputerBuilder {
-            computer: Computer {
-                serial: String::from("AG23385103"),
-                memory: None,
-                hdd: None,
-                gpu: None,
-            }
+            serial,
+            memory: Missing,
+            hdd: Missing,
+            gpu: None,
+        }
+    }
+}
+
+impl<Memory, Hdd> ComputerBuilder<Memory, Hdd> {
+    fn configure_memory(self, size: u32) -> ComputerBuilder<Configured, Hdd> {
+        ComputerBuilder {
+            serial: self.serial,
+            memory: Configured(size),
+            hdd: self.hdd,
+            gpu: self.gpu,
         }
     }
 
-    fn configure_memory(&mut self, size: u32) {
-        self.computer.memory = Some(size);
+    fn configure_hdd(self, size: u32) -> ComputerBuilder<Memory, Configured> {
+        ComputerBuilder {
+            serial: self.serial,
+            memory: self.memory,
+            hdd: Configured(size),
+            gpu: self.gpu,
+        }
     }
 
-    fn configure_hdd(&mut self, size: u32) {
-        self.computer.hdd = Some(size);
+    fn configure_gpu(mut self, gpu: String) -> ComputerBuilder<Memory, Hdd> {
+        self.gpu = Some(gpu);
+        self
     }
-    fn configure_gpu(&mut self, gpu: String) {
-        self.computer.gpu = Some(gpu);
+}
+
+impl ComputerBuilder<Configured, Configured> {
+    fn build(self) -> Computer {
+        Computer {
+            serial: self.serial,
+            memory: self.memory.0,
+            hdd: self.hdd.0,
+            gpu: self.gpu,
+        }
     }
+}
 
-    fn get_info(&self) -> String {
-        let info = self.computer.get_info().clone();
-        info
+struct GpuModel {
+    name: &'static str,
+    /// Memory the card needs in GB.
+    min_memory: u32,
+    /// In cents.
+    price: u64,
+}
+
+/// Graphics cards which can be installed.
+const GPUS: &[GpuModel] = &[
+    GpuModel { name: "GeForce GTX 650 Ti", min_memory: 4, price: 9900 },
+    GpuModel { name: "GeForce GTX 1660", min_memory: 8, price: 21900 },
+    GpuModel { name: "GeForce RTX 3080", min_memory: 16, price: 69900 },
+    GpuModel { name: "Radeon Pro W6800", min_memory: 32, price: 224900 },
+];
+// In cents.
+const MEMORY_PRICE_PER_GB: u64 = 350;
+const HDD_PRICE_PER_GB: u64 = 4;
+
+fn find_gpu(name: &str) -> Option<&'static GpuModel> {
+    GPUS.iter().find(|model| model.name == name)
+}
+
+const MEMORY_RANGE: (u32, u32) = (4, 256);
+const HDD_RANGE: (u32, u32) = (128, 16384);
+
+/// What the engineer is asked to construct.
+#[derive(Debug, Clone)]
+struct Configuration {
+    memory: u32,
+    hdd: u32,
+    gpu: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum ConfigurationError {
+    MemoryOutOfRange(u32),
+    HddOutOfRange(u32),
+    UnknownGpu(String),
+    /// The graphics card needs more memory.
+    InsufficientMemoryForGpu { gpu: String, required: u32, memory: u32 },
+    /// All the serial numbers are used.
+    NoSerialNumberLeft,
+}
+
+impl fmt::Display for ConfigurationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigurationError::MemoryOutOfRange(memory) =>
+                write!(f, "Memory must be from {}GB to {}GB: {}GB", MEMORY_RANGE.0, MEMORY_RANGE.1, memory),
+            ConfigurationError::HddOutOfRange(hdd) =>
+                write!(f, "Hard disk must be from {}GB to {}GB: {}GB", HDD_RANGE.0, HDD_RANGE.1, hdd),
+            ConfigurationError::UnknownGpu(gpu) => write!(f, "Unknown graphics card: {}", gpu),
+            ConfigurationError::InsufficientMemoryForGpu { gpu, required, memory } =>
+                write!(f, "{} needs {}GB of memory or more: {}GB", gpu, required, memory),
+            ConfigurationError::NoSerialNumberLeft => write!(f, "No serial number is left"),
+        }
+    }
+}
+
+impl Configuration {
+    /// Check all the rules and return every violation.
+    fn validate(&self) -> Result<(), Vec<ConfigurationError>> {
+        let mut errors = Vec::new();
+
+        if !(MEMORY_RANGE.0..=MEMORY_RANGE.1).contains(&self.memory) {
+            errors.push(ConfigurationError::MemoryOutOfRange(self.memory));
+        }
+        if !(HDD_RANGE.0..=HDD_RANGE.1).contains(&self.hdd) {
+            errors.push(ConfigurationError::HddOutOfRange(self.hdd));
+        }
+        if let Some(gpu) = &self.gpu {
+            match find_gpu(gpu) {
+                None => errors.push(ConfigurationError::UnknownGpu(gpu.clone())),
+                Some(model) if self.memory < model.min_memory => {
+                    errors.push(ConfigurationError::InsufficientMemoryForGpu {
+                        gpu: gpu.clone(),
+                        required: model.min_memory,
+                        memory: self.memory,
+                    });
+                },
+                Some(_) => {},
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Preset {
+    Gaming,
+    Office,
+    Server,
+}
+
+impl Preset {
+    fn from_name(name: &str) -> Option<Preset> {
+        match name {
+            "gaming" => Some(Preset::Gaming),
+            "office" => Some(Preset::Office),
+            "server" => Some(Preset::Server),
+            _ => None,
+        }
+    }
+
+    fn configuration(self) -> Configuration {
+        match self {
+            Preset::Gaming => Configuration {
+                memory: 32,
+                hdd: 2048,
+                gpu: Some(String::from("GeForce RTX 3080")),
+            },
+            Preset::Office => Configuration {
+                memory: 8,
+                hdd: 512,
+                gpu: None,
+            },
+            Preset::Server => Configuration {
+                memory: 128,
+                hdd: 8192,
+                gpu: None,
+            },
+        }
     }
 }
 
 struct HardwareEngineer {
-    builder: Option<ComputerBuilder>,
+    serials: SerialNumberGenerator,
+    computer: Option<Computer>,
 }
 
 impl HardwareEngineer {
-    fn construct_computer(&mut self, memory: u32, hdd: u32, gpu: String) {
-        self.builder = Some(ComputerBuilder::new());
-        
-        self.builder.as_mut().unwrap().configure_memory(memory);
-        self.builder.as_mut().unwrap().configure_hdd(hdd);
-        self.builder.as_mut().unwrap().configure_gpu(gpu);
+    fn new(serials: SerialNumberGenerator) -> HardwareEngineer {
+        HardwareEngineer {
+            serials,
+            computer: None,
+        }
+    }
+
+    /// Nothing is constructed if the configuration breaks any rule,
+    /// and the computer constructed before is taken away.
+    fn construct_computer(&mut self, configuration: Configuration) -> Result<(), Vec<ConfigurationError>> {
+        self.computer = None;
+        configuration.validate()?;
+
+        let serial = self.serials.generate().ok_or_else(|| vec![ConfigurationError::NoSerialNumberLeft])?;
+        let mut builder = ComputerBuilder::new(serial)
+            .configure_memory(configuration.memory)
+            .configure_hdd(configuration.hdd);
+        if let Some(gpu) = configuration.gpu {
+            builder = builder.configure_gpu(gpu);
+        }
+
+        self.computer = Some(builder.build());
+        Ok(())
+    }
+
+    /// Construct a computer from a preset changed by `customize`.
+    fn construct_from_preset<F>(&mut self, preset: Preset, customize: F) -> Result<(), Vec<ConfigurationError>>
+    where
+        F: FnOnce(&mut Configuration),
+    {
+        let mut configuration = preset.configuration();
+        customize(&mut configuration);
+        self.construct_computer(configuration)
     }
 
-    fn get_computer_info(&self) -> String {
-        self.builder.as_ref().unwrap().get_info()
+    /// `None` until a computer is constructed.
+    fn get_computer_info(&self) -> Option<String> {
+        self.computer().map(|computer| computer.get_info())
     }
+
+    fn computer(&self) -> Option<&Computer> {
+        self.computer.as_ref()
+    }
+}
+
+fn print_computer(engineer: &HardwareEngineer, result: Result<(), Vec<ConfigurationError>>) {
+    match result {
+        Ok(()) => {
+            if let Some(computer) = engineer.get_computer_info() {
+                println!("{}", computer);
+            }
+        },
+        Err(errors) => {
+            println!("Invalid configuration:");
+            for error in errors {
+                println!("  {}", error);
+            }
+        },
+    }
+    println!();
 }
 
 fn main() {
-    let mut engineer = HardwareEngineer { builder: None };
-    engineer.construct_computer(
-        500,
-        8,
-        String::from("GeForce GTX 650 Ti")
-    );
-
-    let computer = engineer.get_computer_info();
-    println!("{}", computer);
+    let mut engineer = HardwareEngineer::new(SerialNumberGenerator::new("AG", 23385103));
+
+    // Memory and hard disk are swapped.
+    let result = engineer.construct_computer(Configuration {
+        memory: 500,
+        hdd: 8,
+        gpu: Some(String::from("GeForce GTX 650 Ti")),
+    });
+    print_computer(&engineer, result);
+
+    let result = engineer.construct_computer(Configuration {
+        memory: 8,
+        hdd: 500,
+        gpu: Some(String::from("GeForce GTX 650 Ti")),
+    });
+    print_computer(&engineer, result);
+
+    for name in &["gaming", "office", "server"] {
+        let preset = Preset::from_name(name).unwrap();
+        println!("Preset: {}", name);
+        let result = engineer.construct_from_preset(preset, |_| {});
+        print_computer(&engineer, result);
+    }
+
+    println!("Preset: office with more memory and a graphics card");
+    let result = engineer.construct_from_preset(Preset::Office, |configuration| {
+        configuration.memory = 16;
+        configuration.gpu = Some(String::from("GeForce GTX 1660"));
+    });
+    print_computer(&engineer, result);
+
+    if let Some(computer) = engineer.computer() {
+        match computer.bill_of_materials() {
+            Ok(bill) => {
+                println!("{}\n", bill);
+                println!("{}\n", bill.to_json());
+                print!("{}", bill.to_csv());
+            },
+            Err(e) => println!("{}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn configuration(memory: u32, hdd: u32, gpu: Option<&str>) -> Configuration {
+        Configuration { memory, hdd, gpu: gpu.map(String::from) }
+    }
+
+    fn computer(memory: u32, hdd: u32, gpu: Option<&str>) -> Computer {
+        let builder = ComputerBuilder::new(String::from("AG00000001"))
+            .configure_memory(memory)
+            .configure_hdd(hdd);
+        match gpu {
+            Some(gpu) => builder.configure_gpu(String::from(gpu)).build(),
+            None => builder.build(),
+        }
+    }
+
+    fn engineer() -> HardwareEngineer {
+        HardwareEngineer::new(SerialNumberGenerator::new("AG", 1))
+    }
+
+    #[test]
+    fn memory_and_hdd_ranges() {
+        assert_eq!(configuration(MEMORY_RANGE.0, HDD_RANGE.0, None).validate(), Ok(()));
+        assert_eq!(configuration(MEMORY_RANGE.1, HDD_RANGE.1, None).validate(), Ok(()));
+
+        assert_eq!(
+            configuration(MEMORY_RANGE.0 - 1, HDD_RANGE.1 + 1, None).validate(),
+            Err(vec![
+                ConfigurationError::MemoryOutOfRange(MEMORY_RANGE.0 - 1),
+                ConfigurationError::HddOutOfRange(HDD_RANGE.1 + 1),
+            ])
+        );
+        assert_eq!(
+            configuration(MEMORY_RANGE.1 + 1, HDD_RANGE.0 - 1, None).validate(),
+            Err(vec![
+                ConfigurationError::MemoryOutOfRange(MEMORY_RANGE.1 + 1),
+                ConfigurationError::HddOutOfRange(HDD_RANGE.0 - 1),
+            ])
+        );
+    }
+
+    #[test]
+    fn graphics_cards() {
+        assert_eq!(configuration(16, 512, Some("GeForce RTX 3080")).validate(), Ok(()));
+        assert_eq!(
+            configuration(8, 512, Some("GeForce RTX 3080")).validate(),
+            Err(vec![ConfigurationError::InsufficientMemoryForGpu {
+                gpu: String::from("GeForce RTX 3080"),
+                required: 16,
+                memory: 8,
+            }])
+        );
+        assert_eq!(
+            configuration(8, 512, Some("Voodoo 2")).validate(),
+            Err(vec![ConfigurationError::UnknownGpu(String::from("Voodoo 2"))])
+        );
+    }
+
+    #[test]
+    fn presets() {
+        for name in &["gaming", "office", "server"] {
+            let preset = Preset::from_name(name).unwrap();
+            assert_eq!(preset.configuration().validate(), Ok(()), "{} is invalid", name);
+        }
+        assert!(Preset::from_name("Gaming").is_none());
+
+        let mut engineer = engineer();
+        engineer.construct_from_preset(Preset::Office, |configuration| configuration.memory = 16).unwrap();
+        let computer = engineer.computer().unwrap();
+        assert_eq!((computer.memory, computer.hdd, computer.gpu.as_deref()), (16, 512, None));
+    }
+
+    #[test]
+    fn failure_takes_computer_away() {
+        let mut engineer = engineer();
+        assert!(engineer.get_computer_info().is_none());
+
+        engineer.construct_from_preset(Preset::Gaming, |_| {}).unwrap();
+        assert!(engineer.get_computer_info().unwrap().starts_with("Serial Number: AG00000001\n"));
+
+        let result = engineer.construct_from_preset(Preset::Gaming, |configuration| configuration.memory = 8);
+        assert_eq!(result.unwrap_err().len(), 1);
+        assert!(engineer.computer().is_none());
+
+        // No serial number is used by the failed one.
+        engineer.construct_from_preset(Preset::Server, |_| {}).unwrap();
+        assert_eq!(engineer.computer().unwrap().serial, "AG00000002");
+    }
+
+    #[test]
+    fn serial_numbers() {
+        let mut serials = SerialNumberGenerator::new("AG", MAX_SERIAL);
+        assert_eq!(serials.generate().as_deref(), Some("AG99999999"));
+        assert_eq!(serials.generate(), None);
+        assert_eq!(serials.generate(), None);
+        assert_eq!(SerialNumberGenerator::new("AG", u32::MAX).generate(), None);
+
+        let mut serials = SerialNumberGenerator::new("XY", 7);
+        assert_eq!((serials.generate(), serials.generate()), (Some(String::from("XY00000007")), Some(String::from("XY00000008"))));
+
+        let mut engineer = HardwareEngineer::new(SerialNumberGenerator::new("AG", MAX_SERIAL));
+        engineer.construct_from_preset(Preset::Office, |_| {}).unwrap();
+        assert_eq!(engineer.computer().unwrap().serial, "AG99999999");
+        assert_eq!(
+            engineer.construct_from_preset(Preset::Office, |_| {}),
+            Err(vec![ConfigurationError::NoSerialNumberLeft])
+        );
+        assert!(engineer.computer().is_none());
+    }
+
+    #[test]
+    fn bill_totals() {
+        let bill = computer(16, 1000, Some("GeForce GTX 1660")).bill_of_materials().unwrap();
+        let prices: Vec<Price> = bill.items.iter().map(|item| item.price).collect();
+        assert_eq!(prices, vec![Price(5600), Price(4000), Price(21900)]);
+        assert_eq!(bill.total, Price(31500));
+        assert_eq!(bill.total.to_string(), "315.00");
+
+        let bill = computer(8, 500, None).bill_of_materials().unwrap();
+        assert_eq!(bill.items.len(), 2);
+        assert_eq!(bill.total.to_string(), "48.00");
+
+        assert_eq!(
+            computer(8, 500, Some("Voodoo 2")).bill_of_materials().unwrap_err(),
+            ConfigurationError::UnknownGpu(String::from("Voodoo 2"))
+        );
+    }
+
+    #[test]
+    fn bill_of_unvalidated_sizes() {
+        let bill = computer(u32::MAX, u32::MAX, Some("Radeon Pro W6800")).bill_of_materials().unwrap();
+
+        assert_eq!(bill.total, Price(u64::from(u32::MAX) * (350 + 4) + 224900));
+    }
+
+    #[test]
+    fn bill_exports() {
+        let bill = computer(8, 500, Some("GeForce GTX 650 Ti")).bill_of_materials().unwrap();
+
+        let json: serde_json::Value = serde_json::from_str(&bill.to_json()).unwrap();
+        assert_eq!(json["serial"], "AG00000001");
+        assert_eq!(json["items"][2]["part"], "Graphics Card");
+        assert_eq!(json["items"][2]["price"], "99.00");
+        assert_eq!(json["total"], "147.00");
+
+        assert_eq!(bill.to_csv(), "serial,part,description,price\n\
+            AG00000001,Memory,8GB,28.00\n\
+            AG00000001,Hard Disk,500GB,20.00\n\
+            AG00000001,Graphics Card,GeForce GTX 650 Ti,99.00\n\
+            AG00000001,Total,,147.00\n");
+    }
 }
@@ -1,70 +1,385 @@
+use std::error::Error;
+use std::fmt;
+
+/// Format an amount in cents.
+fn format_price(cents: u32) -> String {
+    format!("{}.{:02}", cents / 100, cents % 100)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Size {
+    Small,
+    Medium,
+    Large,
+}
+
+impl Size {
+    fn name(self) -> &'static str {
+        match self {
+            Size::Small => "Small",
+            Size::Medium => "Medium",
+            Size::Large => "Large",
+        }
+    }
+
+    /// In cents.
+    fn price(self) -> u32 {
+        match self {
+            Size::Small => 800,
+            Size::Medium => 1000,
+            Size::Large => 1300,
+        }
+    }
+
+    /// Total quantity of toppings which fit on the pizza.
+    fn max_toppings(self) -> u32 {
+        match self {
+            Size::Small => 4,
+            Size::Medium => 6,
+            Size::Large => 8,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Crust {
+    Thin,
+    Regular,
+    Stuffed,
+    GlutenFree,
+}
+
+impl Crust {
+    fn name(self) -> &'static str {
+        match self {
+            Crust::Thin => "thin",
+            Crust::Regular => "regular",
+            Crust::Stuffed => "stuffed",
+            Crust::GlutenFree => "gluten-free",
+        }
+    }
+
+    /// In cents, added to the price of the size.
+    fn price(self) -> u32 {
+        match self {
+            Crust::Thin | Crust::Regular => 0,
+            Crust::Stuffed => 250,
+            Crust::GlutenFree => 200,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Topping {
+    Garlic,
+    ExtraCheese,
+    VeganCheese,
+    Pepperoni,
+    Ham,
+    Mushrooms,
+    Olives,
+    Pineapple,
+    Anchovies,
+}
+
+/// Toppings which cannot be on the same pizza.
+const INCOMPATIBLE_TOPPINGS: &[(Topping, Topping)] = &[
+    (Topping::ExtraCheese, Topping::VeganCheese),
+    (Topping::Pineapple, Topping::Anchovies),
+];
+
+impl Topping {
+    fn name(self) -> &'static str {
+        match self {
+            Topping::Garlic => "Garlic",
+            Topping::ExtraCheese => "Extra cheese",
+            Topping::VeganCheese => "Vegan cheese",
+            Topping::Pepperoni => "Pepperoni",
+            Topping::Ham => "Ham",
+            Topping::Mushrooms => "Mushrooms",
+            Topping::Olives => "Olives",
+            Topping::Pineapple => "Pineapple",
+            Topping::Anchovies => "Anchovies",
+        }
+    }
+
+    /// In cents per portion.
+    fn price(self) -> u32 {
+        match self {
+            Topping::Garlic => 50,
+            Topping::ExtraCheese | Topping::VeganCheese => 100,
+            Topping::Mushrooms | Topping::Olives | Topping::Pineapple => 75,
+            Topping::Pepperoni | Topping::Ham => 125,
+            Topping::Anchovies => 150,
+        }
+    }
+
+    /// Portions allowed on one pizza.
+    fn max_quantity(self) -> u32 {
+        match self {
+            Topping::Garlic | Topping::Anchovies => 1,
+            Topping::ExtraCheese | Topping::VeganCheese => 2,
+            _ => 3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum PizzaError {
+    IncompatibleToppings(Topping, Topping),
+    TooMuchTopping { topping: Topping, quantity: u32, max: u32 },
+    TooManyToppings { size: Size, quantity: u32, max: u32 },
+    ZeroQuantity(Topping),
+    CrustNotAvailable { crust: Crust, size: Size },
+}
+
+impl Error for PizzaError {}
+
+impl fmt::Display for PizzaError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PizzaError::IncompatibleToppings(a, b) =>
+                write!(f, "{} and {} cannot be on the same pizza", a.name(), b.name()),
+            PizzaError::TooMuchTopping { topping, quantity, max } =>
+                write!(f, "Up to {} of {} is allowed: {}", max, topping.name(), quantity),
+            PizzaError::TooManyToppings { size, quantity, max } =>
+                write!(f, "{} pizza takes up to {} toppings: {}", size.name(), max, quantity),
+            PizzaError::ZeroQuantity(topping) => write!(f, "Quantity of {} must not be 0", topping.name()),
+            PizzaError::CrustNotAvailable { crust, size } =>
+                write!(f, "{} crust is not available for {} pizza", crust.name(), size.name()),
+        }
+    }
+}
+
 struct Pizza {
-    garlic: bool,
-    extra_cheese: bool,
+    size: Size,
+    crust: Crust,
+    /// Toppings in the order they were added, with their quantities.
+    toppings: Vec<(Topping, u32)>,
 }
 
 impl Pizza {
     fn new(builder: PizzaBuilder) -> Pizza {
         Pizza {
-            garlic: builder.garlic,
-            extra_cheese: builder.extra_cheese,
+            size: builder.size,
+            crust: builder.crust,
+            toppings: builder.toppings,
         }
     }
 
-    fn get_info(&self) -> String {
-        let garlic = {
-            if self.garlic == true {
-                "yes"
-            } else {
-                "no"
-            }
-        };
-
-        let cheese = {
-            if self.extra_cheese == true {
-                "yes"
-            } else {
-                "no"
-            }
-        };
+    /// In cents.
+    fn price(&self) -> u32 {
+        let toppings: u32 = self.toppings.iter()
+            .map(|&(topping, quantity)| topping.price() * quantity)
+            .sum();
+        self.size.price() + self.crust.price() + toppings
+    }
 
-        let info = format!("Garlic: {}\nExtra cheese: {}",
-            garlic,
-            cheese
-        );
-        info
+    /// Itemised receipt.
+    fn get_info(&self) -> String {
+        let mut lines = vec![format!("{:<32}{:>8}", format!("{} pizza", self.size.name()), format_price(self.size.price()))];
+        lines.push(format!("  {:<30}{:>8}", format!("{} crust", self.crust.name()), format_price(self.crust.price())));
+        for &(topping, quantity) in &self.toppings {
+            lines.push(format!("  {:<30}{:>8}",
+                format!("{} x{}", topping.name(), quantity),
+                format_price(topping.price() * quantity)
+            ));
+        }
+        lines.push(format!("{:<32}{:>8}", "Total", format_price(self.price())));
+        lines.join("\n")
     }
 }
 
 struct PizzaBuilder {
-    garlic: bool,
-    extra_cheese: bool,
+    size: Size,
+    crust: Crust,
+    toppings: Vec<(Topping, u32)>,
 }
 
 impl PizzaBuilder {
     fn new() -> PizzaBuilder {
         PizzaBuilder {
-            garlic: false,
-            extra_cheese: false,
+            size: Size::Medium,
+            crust: Crust::Regular,
+            toppings: Vec::new(),
         }
     }
 
-    fn add_garlic(mut self) -> Self{
-        self.garlic = true;
+    fn size(mut self, size: Size) -> Self {
+        self.size = size;
         self
     }
 
-    fn add_extra_cheese(mut self) -> Self{
-        self.extra_cheese = true;
+    fn crust(mut self, crust: Crust) -> Self {
+        self.crust = crust;
         self
     }
 
-    fn build(self) -> Pizza {
-        Pizza::new(self)
+    /// Adding the same topping again increases its quantity.
+    /// A quantity too large to count is still rejected by `build`.
+    fn add_topping(mut self, topping: Topping, quantity: u32) -> Self {
+        match self.toppings.iter_mut().find(|(added, _)| *added == topping) {
+            Some((_, added)) => *added = added.saturating_add(quantity),
+            None => self.toppings.push((topping, quantity)),
+        }
+        self
+    }
+
+    fn add_garlic(self) -> Self {
+        self.add_topping(Topping::Garlic, 1)
+    }
+
+    fn add_extra_cheese(self) -> Self {
+        self.add_topping(Topping::ExtraCheese, 1)
+    }
+
+    fn validate(&self) -> Result<(), PizzaError> {
+        if self.crust == Crust::Stuffed && self.size == Size::Small {
+            return Err(PizzaError::CrustNotAvailable { crust: self.crust, size: self.size });
+        }
+
+        for &(topping, quantity) in &self.toppings {
+            if quantity == 0 {
+                return Err(PizzaError::ZeroQuantity(topping));
+            }
+            if quantity > topping.max_quantity() {
+                return Err(PizzaError::TooMuchTopping { topping, quantity, max: topping.max_quantity() });
+            }
+        }
+
+        let has = |topping: Topping| self.toppings.iter().any(|&(added, _)| added == topping);
+        if let Some(&(a, b)) = INCOMPATIBLE_TOPPINGS.iter().find(|&&(a, b)| has(a) && has(b)) {
+            return Err(PizzaError::IncompatibleToppings(a, b));
+        }
+
+        let quantity = self.toppings.iter().map(|&(_, quantity)| quantity).sum();
+        if quantity > self.size.max_toppings() {
+            return Err(PizzaError::TooManyToppings { size: self.size, quantity, max: self.size.max_toppings() });
+        }
+
+        Ok(())
+    }
+
+    fn build(self) -> Result<Pizza, PizzaError> {
+        self.validate()?;
+        Ok(Pizza::new(self))
     }
 }
 
 fn main() {
-    let pizza = PizzaBuilder::new().add_garlic().add_extra_cheese().build();
-    println!("{}", pizza.get_info());
+    let orders = vec![
+        PizzaBuilder::new().add_garlic().add_extra_cheese(),
+        PizzaBuilder::new()
+            .size(Size::Large)
+            .crust(Crust::Stuffed)
+            .add_topping(Topping::Pepperoni, 2)
+            .add_topping(Topping::Mushrooms, 1)
+            .add_extra_cheese()
+            .add_extra_cheese(),
+        PizzaBuilder::new().add_extra_cheese().add_topping(Topping::VeganCheese, 1),
+        PizzaBuilder::new().size(Size::Small).crust(Crust::Thin).add_topping(Topping::Anchovies, 2),
+        PizzaBuilder::new()
+            .size(Size::Small)
+            .crust(Crust::GlutenFree)
+            .add_topping(Topping::Ham, 3)
+            .add_topping(Topping::Olives, 2),
+    ];
+
+    for order in orders {
+        match order.build() {
+            Ok(pizza) => println!("{}", pizza.get_info()),
+            Err(e) => println!("Cannot make the pizza: {}", e),
+        }
+        println!();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn price(builder: PizzaBuilder) -> u32 {
+        builder.build().unwrap().price()
+    }
+
+    #[test]
+    fn size_and_crust_prices() {
+        assert_eq!(price(PizzaBuilder::new()), 1000);
+        assert_eq!(price(PizzaBuilder::new().size(Size::Small).crust(Crust::Thin)), 800);
+        assert_eq!(price(PizzaBuilder::new().size(Size::Large).crust(Crust::Stuffed)), 1550);
+        assert_eq!(price(PizzaBuilder::new().size(Size::Small).crust(Crust::GlutenFree)), 1000);
+        assert_eq!(
+            PizzaBuilder::new().size(Size::Small).crust(Crust::Stuffed).build().err(),
+            Some(PizzaError::CrustNotAvailable { crust: Crust::Stuffed, size: Size::Small })
+        );
+    }
+
+    #[test]
+    fn duplicate_toppings_are_merged() {
+        let pizza = PizzaBuilder::new().add_extra_cheese().add_topping(Topping::Ham, 1).add_extra_cheese().build().unwrap();
+
+        assert_eq!(pizza.toppings, vec![(Topping::ExtraCheese, 2), (Topping::Ham, 1)]);
+        assert_eq!(pizza.price(), 1000 + 200 + 125);
+    }
+
+    #[test]
+    fn topping_limits() {
+        assert_eq!(
+            PizzaBuilder::new().add_garlic().add_garlic().build().err(),
+            Some(PizzaError::TooMuchTopping { topping: Topping::Garlic, quantity: 2, max: 1 })
+        );
+        assert_eq!(
+            PizzaBuilder::new().add_topping(Topping::Ham, 0).build().err(),
+            Some(PizzaError::ZeroQuantity(Topping::Ham))
+        );
+
+        let full = PizzaBuilder::new().size(Size::Small).add_topping(Topping::Ham, 3).add_garlic();
+        assert!(full.build().is_ok());
+        let over = PizzaBuilder::new().size(Size::Small).add_topping(Topping::Ham, 3).add_topping(Topping::Olives, 2);
+        assert_eq!(
+            over.build().err(),
+            Some(PizzaError::TooManyToppings { size: Size::Small, quantity: 5, max: 4 })
+        );
+    }
+
+    #[test]
+    fn huge_quantity_does_not_overflow() {
+        let builder = PizzaBuilder::new().add_topping(Topping::Ham, u32::MAX).add_topping(Topping::Ham, 2);
+
+        assert_eq!(
+            builder.build().err(),
+            Some(PizzaError::TooMuchTopping { topping: Topping::Ham, quantity: u32::MAX, max: 3 })
+        );
+    }
+
+    #[test]
+    fn incompatible_toppings() {
+        assert_eq!(
+            PizzaBuilder::new().add_topping(Topping::VeganCheese, 1).add_extra_cheese().build().err(),
+            Some(PizzaError::IncompatibleToppings(Topping::ExtraCheese, Topping::VeganCheese))
+        );
+        assert_eq!(
+            PizzaBuilder::new().add_topping(Topping::Pineapple, 1).add_topping(Topping::Anchovies, 1).build().err(),
+            Some(PizzaError::IncompatibleToppings(Topping::Pineapple, Topping::Anchovies))
+        );
+    }
+
+    #[test]
+    fn receipt_total() {
+        let pizza = PizzaBuilder::new()
+            .size(Size::Large)
+            .crust(Crust::Stuffed)
+            .add_topping(Topping::Pepperoni, 2)
+            .add_garlic()
+            .build()
+            .unwrap();
+        let receipt = pizza.get_info();
+        let lines: Vec<&str> = receipt.lines().collect();
+
+        assert_eq!(lines.len(), 5);
+        assert_eq!(lines[0], format!("{:<32}{:>8}", "Large pizza", "13.00"));
+        assert_eq!(lines[2], format!("  {:<30}{:>8}", "Pepperoni x2", "2.50"));
+        assert_eq!(lines[4], format!("{:<32}{:>8}", "Total", "18.50"));
+        assert_eq!(pizza.price(), 1850);
+    }
 }
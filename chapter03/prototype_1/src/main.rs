@@ -1,97 +1,31 @@
 use std::collections::HashMap;
+use std::env;
+use std::path::Path;
+use std::process;
 
-// Since there's no feature like '**kwargs' of Python in Rust,
-// and additional fields cannot be add to defined struct,
-// HashMap is used instead in this example.
-#[derive(Clone)]
-struct Website {
-    info: HashMap<String, String>,
-}
- 
-impl Website {
-    fn new(name: String, domain: String, description: String, author: String, add_info: HashMap<String, String>) -> Website {
-        // Examples of optional attributes (add_info):
-        // category, creation_date, technologies, keywords.
-        let mut default_values = HashMap::new();
-
-        default_values.insert(String::from("name"), name);
-        default_values.insert(String::from("domain"), domain);
-        default_values.insert(String::from("description"), description);
-        default_values.insert(String::from("author"), author);
-
-        for (key, value) in &add_info {
-            default_values.insert(String::from(key.clone()), value.clone());
-        }
-
-        Website { info: default_values }
-    }
-
-    fn get_info(&self) -> String {
-        let mut summary = vec![format!("Website: \"{}\"\n", self.info["name"])];
-
-        let infos = self.info.clone();
-        // HashMap cannot be sorted like Python.
-        // So, get sorted keys of it separately and print infos orderly.
-        // Reference: https://doc.rust-lang.org/std/collections/struct.HashMap.html#method.iter
-        let sorted_keys = sorted_keys(&infos);
-
-        for key in sorted_keys {
-            if key == String::from("name") {
-                continue
-            }
-            summary.push(format!("{}: {}\n", key, infos[&key]));
-        }
-
-        let mut summary_for_print = String::new();
-
-        for entry in summary {
-            summary_for_print.push_str(&entry);
-        }
+use chrono::NaiveDate;
 
-        summary_for_print
-    }
-}
+mod prototype;
+mod query;
+mod website;
 
-struct Prototype {
-    objects: HashMap<String, Website>,
-}
-
-impl Prototype {
-    fn new() -> Prototype {
-        Prototype {
-            objects: HashMap::new(),
-        }
-    }
-
-    fn register(&mut self, identifier: String, obj: Website) {
-        self.objects.insert(identifier, obj);
-    }
-
-    fn unregister(&mut self, identifier: String) {
-        self.objects.remove(&identifier);
-    }
+use prototype::Prototype;
+use query::Query;
+use website::{diff, get_diff_info, Value, Website};
 
-    fn clone(&mut self, identifier: String, new_infos: HashMap<String, String>) -> Result<Website, String>  {
-        let found = self.objects.get(&identifier);
+const USAGE: &str = "Usage:
+  prototype_1 [--registry FILE] list
+  prototype_1 [--registry FILE] show ID
+  prototype_1 [--registry FILE] register ID NAME DOMAIN DESCRIPTION AUTHOR [KEY=VALUE ...]
+  prototype_1 [--registry FILE] unregister ID
+  prototype_1 [--registry FILE] clone ID [--as NEW_ID] [--unset KEY ...] [KEY=VALUE ...]
+  prototype_1 [--registry FILE] diff ID1 ID2
+  prototype_1 [--registry FILE] find [--where KEY=VALUE] [--contains KEY=TEXT] [--keyword WORD]
+                                     [--from DATE] [--to DATE] [--sort KEY]
 
-        match found {
-            Some(current_obj) => {
-                let mut new_obj = current_obj.clone();
+The registry is prototypes.json by default. Without arguments, an example is run.";
 
-                // If a key already exists, the value will be overwritten.
-                for (new_key, new_value) in new_infos {
-                    new_obj.info.insert(new_key.clone(), new_value.clone());
-                }
-
-                Ok(new_obj)
-            },
-            None => Err(format!("Incorrect object identifier: {}", identifier)),
-        }
-    }
-}
-
-
-fn sorted_keys(hashmap: &HashMap<String, String>) -> Vec<String> {
+fn sorted_keys<V>(hashmap: &HashMap<String, V>) -> Vec<String> {
     // Get keys
     let mut keys: Vec<String> = Vec::new();
     
@@ -104,20 +38,24 @@ fn sorted_keys(hashmap: &HashMap<String, String>) -> Vec<String> {
     keys
 }
  
-fn main() {
-    // Since HashMap can have the type of value specified in advance,
-    // keywords will be set as String comma-breaked.
-    let keywords: String = String::from("python,data,apis,automation");
-    let mut keywords_mapped: HashMap<String, String> = HashMap::new();
-    keywords_mapped.insert(String::from("keywords"), keywords);
-    keywords_mapped.insert(String::from("category"), String::from("Blog"));
-
-    let site1 = Website::new("ContentGardening".to_string(),
-        "contentgardening.com".to_string(),
-        "Automation and data-driven apps".to_string(),
-        "Kamon Ayeva".to_string(),
-        keywords_mapped
-    );
+fn demo() {
+    let mut site1 = Website {
+        category: Some(String::from("Blog")),
+        keywords: vec![
+            String::from("python"),
+            String::from("data"),
+            String::from("apis"),
+            String::from("automation"),
+        ],
+        ..Website::new("ContentGardening".to_string(),
+            "contentgardening.com".to_string(),
+            "Automation and data-driven apps".to_string(),
+            "Kamon Ayeva".to_string()
+        )
+    };
+    // Attributes other than the typed ones are kept as extensions.
+    site1.set("technologies", Value::List(vec![String::from("Python"), String::from("Django")])).unwrap();
+    site1.set("articles", Value::Number(120.0)).unwrap();
 
     let mut prototype = Prototype::new();
     let identifier = "ka-cg-1".to_string();
@@ -126,19 +64,228 @@ fn main() {
     // println!("{}", prototype.objects[&"ka-cg-1".to_string()].get_info());
 
     // Prepare to clone site1 with addtional infos
-    let mut site2_add_info: HashMap<String, String> = HashMap::new();
-    site2_add_info.insert("name".to_string(), "ContentGardeningPlayground".to_string());
-    site2_add_info.insert("domain".to_string(), "play.contentgardening.com".to_string());
-    site2_add_info.insert("description".to_string(), "Experimentation for techniques featured on the blog".to_string());
-    site2_add_info.insert("category".to_string(), "Membership site".to_string());
-    site2_add_info.insert("creation_date".to_string(), "2018-08-01".to_string());
+    let mut site2_add_info: HashMap<String, Value> = HashMap::new();
+    site2_add_info.insert("name".to_string(), Value::Text("ContentGardeningPlayground".to_string()));
+    site2_add_info.insert("domain".to_string(), Value::Text("play.contentgardening.com".to_string()));
+    site2_add_info.insert("description".to_string(), Value::Text("Experimentation for techniques featured on the blog".to_string()));
+    site2_add_info.insert("category".to_string(), Value::Text("Membership site".to_string()));
+    site2_add_info.insert("creation_date".to_string(), Value::Date(NaiveDate::from_ymd_opt(2018, 8, 1).unwrap()));
 
     // Clone site1 with addtional infos.
-    let site2 = prototype.clone("ka-cg-1".to_string(), site2_add_info).unwrap();
+    let site2 = prototype.clone("ka-cg-1", site2_add_info).unwrap();
 
     println!("{}", prototype.objects[&"ka-cg-1".to_string()].get_info());
     println!("{}", site2.get_info());
 
     // https://stackoverflow.com/questions/30157258/does-rust-track-unique-object-ids-and-can-we-print-them
     println!("Address of site1 in prototype : {:p} != Address of site2 : {:p}", &prototype.objects[&"ka-cg-1".to_string()], &site2);
+
+    // A registered clone is cloned again, and the chain is kept.
+    prototype.register("ka-cg-2".to_string(), site2);
+    let mut site3_add_info: HashMap<String, Value> = HashMap::new();
+    site3_add_info.insert("category".to_string(), Value::Text("Online course".to_string()));
+    site3_add_info.insert("keywords".to_string(), Value::List(vec!["python".to_string(), "courses".to_string()]));
+    let site3 = prototype.clone("ka-cg-2", site3_add_info).unwrap();
+
+    println!("\n{}", site3.get_provenance());
+    println!("category was set by the clone of {}", site3.origin_of("category").unwrap().from);
+    println!("domain was set by the clone of {}\n", site3.origin_of("domain").unwrap().from);
+    print!("{}", get_diff_info(&diff(&prototype.objects[&"ka-cg-1".to_string()], &site3)));
+
+    // Find the prototypes about python created since 2018, sorted by their names.
+    prototype.register("ka-cg-3".to_string(), site3);
+    let query = Query::new()
+        .has_keyword("python")
+        .created_between(NaiveDate::from_ymd_opt(2018, 1, 1), None)
+        .sort_by("name");
+    println!();
+    for (identifier, website) in prototype.find(&query) {
+        println!("Found {}: {}", identifier, website.name);
+    }
+    // Values are compared with their types.
+    let query = Query::new().equals("creation_date", Value::Date(NaiveDate::from_ymd_opt(2018, 8, 1).unwrap()));
+    for (identifier, _) in prototype.find(&query) {
+        println!("Created on 2018-08-01: {}", identifier);
+    }
+
+    // Overrides must have the types of the attributes.
+    let mut invalid_info: HashMap<String, Value> = HashMap::new();
+    invalid_info.insert("creation_date".to_string(), Value::Text("2018-08-01".to_string()));
+    invalid_info.insert("keywords".to_string(), Value::Text("python,data".to_string()));
+    invalid_info.insert("articles".to_string(), Value::Text("many".to_string()));
+    if let Err(e) = prototype.clone("ka-cg-1", invalid_info) {
+        println!("{}", e);
+    }
+
+    prototype.unregister("ka-cg-1");
+    if let Err(e) = prototype.clone("ka-cg-1", HashMap::new()) {
+        println!("{}", e);
+    }
+}
+
+/// Read `KEY=VALUE` arguments as attributes of `website`.
+fn parse_attributes(website: &Website, args: &[String]) -> Result<HashMap<String, Value>, String> {
+    let mut attributes = HashMap::new();
+
+    for arg in args {
+        let (key, text) = match arg.find('=') {
+            Some(index) => (&arg[..index], &arg[index + 1..]),
+            None => return Err(format!("Not KEY=VALUE: {}", arg)),
+        };
+        let value = website.parse_value(key, text).map_err(|e| e.to_string())?;
+        attributes.insert(String::from(key), value);
+    }
+
+    Ok(attributes)
+}
+
+/// Read the options of `find`. Filters can be given more than once.
+fn parse_query(args: &[String]) -> Result<Query, String> {
+    let mut query = Query::new();
+
+    let split = |arg: &String| match arg.find('=') {
+        Some(index) => Ok((String::from(&arg[..index]), String::from(&arg[index + 1..]))),
+        None => Err(format!("Not KEY=VALUE: {}", arg)),
+    };
+    let date = |arg: &String| NaiveDate::parse_from_str(arg, "%Y-%m-%d")
+        .map_err(|_| format!("Not a date in YYYY-MM-DD: {}", arg));
+
+    let mut args = args.iter();
+    while let Some(option) = args.next() {
+        let value = args.next().ok_or_else(|| format!("{} needs a value", option))?;
+        query = match option.as_str() {
+            "--where" => {
+                let (key, text) = split(value)?;
+                query.equals_text(&key, &text)
+            },
+            "--contains" => {
+                let (key, text) = split(value)?;
+                query.contains(&key, &text)
+            },
+            "--keyword" => query.has_keyword(value),
+            "--from" => query.created_between(Some(date(value)?), None),
+            "--to" => query.created_between(None, Some(date(value)?)),
+            "--sort" => query.sort_by(value),
+            _ => return Err(String::from(USAGE)),
+        };
+    }
+
+    Ok(query)
+}
+
+/// Run a command on the registry, and return whether it's changed.
+fn run_command(prototype: &mut Prototype, command: &str, args: &[String]) -> Result<bool, String> {
+    match (command, args) {
+        ("list", []) => {
+            for identifier in sorted_keys(&prototype.objects) {
+                println!("{}: {}", identifier, prototype.objects[&identifier].name);
+            }
+            Ok(false)
+        },
+        ("show", [identifier]) => {
+            let website = prototype.objects.get(identifier)
+                .ok_or_else(|| format!("Incorrect object identifier: {}", identifier))?;
+            print!("{}", website.get_info());
+            print!("{}", website.get_provenance());
+            Ok(false)
+        },
+        ("diff", [a, b]) => {
+            let get = |identifier: &String| prototype.objects.get(identifier)
+                .ok_or_else(|| format!("Incorrect object identifier: {}", identifier));
+            print!("{}", get_diff_info(&diff(get(a)?, get(b)?)));
+            Ok(false)
+        },
+        ("register", [identifier, name, domain, description, author, attributes @ ..]) => {
+            let mut website = Website::new(name.clone(), domain.clone(), description.clone(), author.clone());
+            for (key, value) in parse_attributes(&website, attributes)? {
+                website.set(&key, value).map_err(|e| e.to_string())?;
+            }
+            if prototype.objects.contains_key(identifier) {
+                println!("Replacing {}", identifier);
+            }
+            prototype.register(identifier.clone(), website);
+            Ok(true)
+        },
+        ("unregister", [identifier]) => {
+            prototype.unregister(identifier)
+                .ok_or_else(|| format!("Incorrect object identifier: {}", identifier))?;
+            Ok(true)
+        },
+        ("clone", [identifier, args @ ..]) => {
+            let mut new_identifier = None;
+            let mut unset = Vec::new();
+            let mut attributes = Vec::new();
+
+            let mut args = args.iter();
+            while let Some(arg) = args.next() {
+                match arg.as_str() {
+                    "--as" => new_identifier = Some(args.next().ok_or_else(|| String::from(USAGE))?),
+                    "--unset" => unset.push(args.next().ok_or_else(|| String::from(USAGE))?.clone()),
+                    _ => attributes.push(arg.clone()),
+                }
+            }
+
+            let website = prototype.objects.get(identifier)
+                .ok_or_else(|| format!("Incorrect object identifier: {}", identifier))?;
+            let new_infos = parse_attributes(website, &attributes)?;
+            let new_website = prototype.clone_with_unset(identifier, new_infos, &unset).map_err(|e| e.to_string())?;
+            print!("{}", new_website.get_info());
+
+            match new_identifier {
+                Some(new_identifier) => {
+                    prototype.register(new_identifier.clone(), new_website);
+                    Ok(true)
+                },
+                None => Ok(false),
+            }
+        },
+        ("find", args) => {
+            let query = parse_query(args)?;
+            for (identifier, website) in prototype.find(&query) {
+                println!("{}: {}", identifier, website.name);
+            }
+            Ok(false)
+        },
+        _ => Err(String::from(USAGE)),
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.is_empty() {
+        demo();
+        return;
+    }
+
+    let (registry, args) = match args.as_slice() {
+        [option, registry, args @ ..] if option == "--registry" => (registry.as_str(), args),
+        args => ("prototypes.json", args),
+    };
+    let (command, args) = match args.split_first() {
+        Some((command, args)) => (command, args),
+        None => {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        },
+    };
+
+    let registry = Path::new(registry);
+    let mut prototype = Prototype::load(registry).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(1);
+    });
+
+    match run_command(&mut prototype, command, args) {
+        Ok(true) => {
+            if let Err(e) = prototype.save(registry) {
+                eprintln!("{}", e);
+                process::exit(1);
+            }
+        },
+        Ok(false) => {},
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(if e == USAGE { 2 } else { 1 });
+        },
+    }
 }
@@ -0,0 +1,315 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::io::Write;
+use std::path::Path;
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::sorted_keys;
+use crate::website::{AttributeError, Derivation, Value, Website};
+
+#[derive(Debug)]
+pub enum CloneError {
+    IncorrectIdentifier(String),
+    /// Every override which does not match the type of its attribute.
+    InvalidAttributes(Vec<AttributeError>),
+}
+
+impl Error for CloneError {}
+
+impl fmt::Display for CloneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CloneError::IncorrectIdentifier(identifier) => write!(f, "Incorrect object identifier: {}", identifier),
+            CloneError::InvalidAttributes(errors) => {
+                let errors: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+                write!(f, "Invalid attributes: {}", errors.join("; "))
+            },
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum RegistryError {
+    Io(io::Error),
+    Json(serde_json::Error),
+}
+
+impl Error for RegistryError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            RegistryError::Io(e) => Some(e),
+            RegistryError::Json(e) => Some(e),
+        }
+    }
+}
+
+impl fmt::Display for RegistryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RegistryError::Io(e) => write!(f, "Cannot access the registry: {}", e),
+            RegistryError::Json(e) => write!(f, "Invalid registry: {}", e),
+        }
+    }
+}
+
+impl From<io::Error> for RegistryError {
+    fn from(e: io::Error) -> Self {
+        RegistryError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for RegistryError {
+    fn from(e: serde_json::Error) -> Self {
+        RegistryError::Json(e)
+    }
+}
+
+/// Websites registered by identifiers to be cloned.
+pub struct Prototype {
+    pub objects: HashMap<String, Website>,
+}
+
+impl Prototype {
+    pub fn new() -> Prototype {
+        Prototype {
+            objects: HashMap::new(),
+        }
+    }
+
+    /// Read a registry saved by `save`. A file which doesn't exist is an empty registry.
+    pub fn load(filepath: &Path) -> Result<Prototype, RegistryError> {
+        let data = match fs::read_to_string(filepath) {
+            Ok(data) => data,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Prototype::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        Ok(Prototype {
+            objects: serde_json::from_str(&data)?,
+        })
+    }
+
+    /// Write the registry to a temporary file next to `filepath` and rename it,
+    /// so that the file is either the old registry or the new one.
+    pub fn save(&self, filepath: &Path) -> Result<(), RegistryError> {
+        // serde_json sorts the keys of objects, so the file doesn't change without changes.
+        let data = serde_json::to_value(&self.objects)?;
+        let data = serde_json::to_string_pretty(&data)?;
+
+        let filename = filepath.file_name().ok_or_else(|| {
+            RegistryError::Io(io::Error::new(io::ErrorKind::InvalidInput, "Not a file path"))
+        })?;
+        // Saves at the same time, in this process or others, write to their own files.
+        static SAVES: AtomicUsize = AtomicUsize::new(0);
+        let temp_filepath = filepath.with_file_name(format!(
+            ".{}.{}.{}.tmp", filename.to_string_lossy(), process::id(), SAVES.fetch_add(1, Ordering::SeqCst)
+        ));
+
+        let result = fs::File::create(&temp_filepath)
+            .and_then(|mut file| {
+                file.write_all(data.as_bytes())?;
+                file.write_all(b"\n")?;
+                file.sync_all()
+            })
+            .and_then(|_| fs::rename(&temp_filepath, filepath));
+        if let Err(e) = result {
+            let _ = fs::remove_file(&temp_filepath);
+            return Err(e.into());
+        }
+
+        Ok(())
+    }
+
+    pub fn register(&mut self, identifier: String, obj: Website) {
+        self.objects.insert(identifier, obj);
+    }
+
+    /// `None` if nothing is registered with the identifier.
+    pub fn unregister(&mut self, identifier: &str) -> Option<Website> {
+        self.objects.remove(identifier)
+    }
+
+    /// The clone records the identifier and the overridden attributes in its provenance,
+    /// so a clone of a registered clone has the whole chain.
+    /// Nothing is cloned if any of `new_infos` has a wrong type.
+    pub fn clone(&self, identifier: &str, new_infos: HashMap<String, Value>) -> Result<Website, CloneError>  {
+        self.clone_with_unset(identifier, new_infos, &[])
+    }
+
+    /// Like `clone`, but the attributes in `unset` are also unset after `new_infos` are set.
+    /// They are recorded as overridden.
+    pub fn clone_with_unset(&self, identifier: &str, new_infos: HashMap<String, Value>, unset: &[String]) -> Result<Website, CloneError> {
+        let found = self.objects.get(identifier);
+
+        match found {
+            Some(current_obj) => {
+                let mut new_obj = current_obj.clone();
+                let mut errors = Vec::new();
+                let mut overridden = sorted_keys(&new_infos);
+                overridden.extend(unset.iter().cloned());
+                overridden.sort();
+                overridden.dedup();
+
+                // If a key already exists, the value will be overwritten.
+                for (new_key, new_value) in new_infos {
+                    if let Err(e) = new_obj.set(&new_key, new_value) {
+                        errors.push(e);
+                    }
+                }
+                for key in unset {
+                    if let Err(e) = new_obj.unset(key) {
+                        errors.push(e);
+                    }
+                }
+
+                if errors.is_empty() {
+                    new_obj.provenance.push(Derivation {
+                        from: String::from(identifier),
+                        overridden,
+                    });
+                    Ok(new_obj)
+                } else {
+                    errors.sort_by(|a, b| a.key.cmp(&b.key));
+                    Err(CloneError::InvalidAttributes(errors))
+                }
+            },
+            None => Err(CloneError::IncorrectIdentifier(String::from(identifier))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::env;
+
+    use chrono::NaiveDate;
+
+    /// Path of a registry unique to the test and the run.
+    fn registry_file(name: &str) -> std::path::PathBuf {
+        env::temp_dir().join(format!("prototype_1_{}_{}.json", process::id(), name))
+    }
+
+    fn prototype() -> Prototype {
+        let mut website = Website::new(String::from("A"), String::from("a.com"), String::from("About A"), String::from("Kamon Ayeva"));
+        website.set("articles", Value::Number(120.0)).unwrap();
+
+        let mut prototype = Prototype::new();
+        prototype.register(String::from("a"), website);
+        prototype
+    }
+
+    #[test]
+    fn wrong_types_clone_nothing() {
+        let prototype = prototype();
+        let mut new_infos = HashMap::new();
+        new_infos.insert(String::from("name"), Value::Text(String::from("B")));
+        new_infos.insert(String::from("keywords"), Value::Text(String::from("python")));
+        new_infos.insert(String::from("articles"), Value::Text(String::from("many")));
+        new_infos.insert(String::from("creation_date"), Value::Text(String::from("2018-08-01")));
+
+        match prototype.clone("a", new_infos) {
+            Err(CloneError::InvalidAttributes(errors)) => {
+                let keys: Vec<&str> = errors.iter().map(|e| e.key.as_str()).collect();
+                assert_eq!(keys, vec!["articles", "creation_date", "keywords"]);
+                assert_eq!(errors[0].to_string(), "articles must be number, not text");
+            },
+            _ => panic!("InvalidAttributes error is expected"),
+        }
+        assert_eq!(prototype.objects["a"].name, "A");
+        assert!(prototype.objects["a"].provenance.is_empty());
+    }
+
+    #[test]
+    fn clone_with_unset_attributes() {
+        let prototype = prototype();
+        let mut new_infos = HashMap::new();
+        new_infos.insert(String::from("creation_date"), Value::Date(NaiveDate::from_ymd_opt(2018, 8, 1).unwrap()));
+        let unset = vec![String::from("author"), String::from("articles")];
+
+        let clone = prototype.clone_with_unset("a", new_infos, &unset).unwrap();
+        assert_eq!(clone.author, "");
+        assert!(clone.extensions.is_empty());
+        assert_eq!(clone.provenance[0].overridden, vec!["articles", "author", "creation_date"]);
+
+        match prototype.clone_with_unset("a", HashMap::new(), &[String::from("name")]) {
+            Err(CloneError::InvalidAttributes(errors)) => assert_eq!(errors[0].key, "name"),
+            _ => panic!("InvalidAttributes error is expected"),
+        }
+    }
+
+    #[test]
+    fn save_and_load() {
+        let filepath = registry_file("round_trip");
+        let mut prototype = prototype();
+        prototype.objects.get_mut("a").unwrap().creation_date = NaiveDate::from_ymd_opt(2018, 8, 1);
+        let clone = prototype.clone("a", HashMap::new()).unwrap();
+        prototype.register(String::from("b"), clone);
+
+        prototype.save(&filepath).unwrap();
+        let loaded = Prototype::load(&filepath).unwrap();
+        assert_eq!(loaded.objects, prototype.objects);
+
+        // Saving the same registry again gives the same file.
+        let data = fs::read_to_string(&filepath).unwrap();
+        loaded.save(&filepath).unwrap();
+        assert_eq!(fs::read_to_string(&filepath).unwrap(), data);
+
+        let leftovers = fs::read_dir(env::temp_dir()).unwrap()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_name().to_string_lossy().starts_with(&format!(".prototype_1_{}_round_trip", process::id())))
+            .count();
+        assert_eq!(leftovers, 0);
+        fs::remove_file(&filepath).unwrap();
+    }
+
+    #[test]
+    fn load_missing_file() {
+        let prototype = Prototype::load(&registry_file("missing")).unwrap();
+
+        assert!(prototype.objects.is_empty());
+    }
+
+    #[test]
+    fn load_malformed_file() {
+        let filepath = registry_file("malformed");
+        fs::write(&filepath, "{\"a\": {\"name\": 1}}").unwrap();
+        let result = Prototype::load(&filepath);
+        fs::remove_file(&filepath).unwrap();
+
+        match result {
+            Err(e @ RegistryError::Json(_)) => assert!(e.to_string().starts_with("Invalid registry: ")),
+            _ => panic!("Json error is expected"),
+        }
+    }
+
+    #[test]
+    fn provenance_of_second_generation() {
+        let mut prototype = prototype();
+        let mut new_infos = HashMap::new();
+        new_infos.insert(String::from("name"), Value::Text(String::from("B")));
+        new_infos.insert(String::from("domain"), Value::Text(String::from("b.com")));
+        new_infos.insert(String::from("category"), Value::Text(String::from("Blog")));
+        let first = prototype.clone("a", new_infos).unwrap();
+        prototype.register(String::from("b"), first);
+
+        let mut new_infos = HashMap::new();
+        new_infos.insert(String::from("keywords"), Value::List(vec![String::from("python")]));
+        new_infos.insert(String::from("category"), Value::Text(String::from("Course")));
+        let second = prototype.clone("b", new_infos).unwrap();
+
+        assert_eq!(second.provenance, vec![
+            Derivation { from: String::from("a"), overridden: vec![String::from("category"), String::from("domain"), String::from("name")] },
+            Derivation { from: String::from("b"), overridden: vec![String::from("category"), String::from("keywords")] },
+        ]);
+        assert_eq!(second.origin_of("category").unwrap().from, "b");
+        assert_eq!(second.origin_of("domain").unwrap().from, "a");
+        assert!(second.origin_of("description").is_none());
+        assert_eq!(second.get_provenance(), "Cloned from a: category, domain, name\nCloned from b: category, keywords\n");
+    }
+}
@@ -0,0 +1,209 @@
+use std::cmp::Ordering;
+
+use chrono::NaiveDate;
+
+use crate::prototype::Prototype;
+use crate::sorted_keys;
+use crate::website::{Value, Website};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Filter {
+    /// The attribute has the value.
+    Equals(String, Value),
+    /// The attribute has the value typed as text, which is read with the type
+    /// of the attribute of each website like `Website::parse_value`.
+    EqualsText(String, String),
+    /// The attribute, as printed by `get_info`, contains the text ignoring case.
+    Contains(String, String),
+    HasKeyword(String),
+    /// Created from the first date to the second, both inclusive.
+    /// An open end is `None`. Websites without a creation date never match.
+    CreatedBetween(Option<NaiveDate>, Option<NaiveDate>),
+}
+
+impl Filter {
+    pub fn matches(&self, website: &Website) -> bool {
+        match self {
+            Filter::Equals(key, value) => website.attributes().get(key) == Some(value),
+            Filter::EqualsText(key, text) => website.attributes().get(key)
+                .is_some_and(|value| website.parse_value(key, text).is_ok_and(|parsed| parsed == *value)),
+            Filter::Contains(key, text) => website.attributes().get(key)
+                .is_some_and(|value| value.to_string().to_lowercase().contains(&text.to_lowercase())),
+            Filter::HasKeyword(keyword) => website.keywords.contains(keyword),
+            Filter::CreatedBetween(from, to) => website.creation_date.is_some_and(|date| {
+                from.is_none_or(|from| from <= date) && to.is_none_or(|to| date <= to)
+            }),
+        }
+    }
+}
+
+/// Websites which match all the filters, sorted by an attribute.
+#[derive(Debug, Clone, Default)]
+pub struct Query {
+    filters: Vec<Filter>,
+    /// Sorted by the identifiers if it's not set.
+    sort_key: Option<String>,
+}
+
+impl Query {
+    pub fn new() -> Query {
+        Query::default()
+    }
+
+    pub fn filter(mut self, filter: Filter) -> Self {
+        self.filters.push(filter);
+        self
+    }
+
+    pub fn equals(self, key: &str, value: Value) -> Self {
+        self.filter(Filter::Equals(String::from(key), value))
+    }
+
+    pub fn equals_text(self, key: &str, text: &str) -> Self {
+        self.filter(Filter::EqualsText(String::from(key), String::from(text)))
+    }
+
+    pub fn contains(self, key: &str, text: &str) -> Self {
+        self.filter(Filter::Contains(String::from(key), String::from(text)))
+    }
+
+    pub fn has_keyword(self, keyword: &str) -> Self {
+        self.filter(Filter::HasKeyword(String::from(keyword)))
+    }
+
+    pub fn created_between(self, from: Option<NaiveDate>, to: Option<NaiveDate>) -> Self {
+        self.filter(Filter::CreatedBetween(from, to))
+    }
+
+    /// Websites without the attribute come last.
+    pub fn sort_by(mut self, key: &str) -> Self {
+        self.sort_key = Some(String::from(key));
+        self
+    }
+
+    pub fn matches(&self, website: &Website) -> bool {
+        self.filters.iter().all(|filter| filter.matches(website))
+    }
+}
+
+/// Values of the same type are compared as the type, and others as printed.
+fn compare_values(a: &Value, b: &Value) -> Ordering {
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => a.partial_cmp(b).unwrap_or(Ordering::Equal),
+        (Value::Date(a), Value::Date(b)) => a.cmp(b),
+        (Value::List(a), Value::List(b)) => a.cmp(b),
+        _ => a.to_string().cmp(&b.to_string()),
+    }
+}
+
+impl Prototype {
+    /// Identifiers and websites which match the query.
+    /// Ties in the sort key are kept in the order of the identifiers.
+    pub fn find(&self, query: &Query) -> Vec<(&String, &Website)> {
+        let mut found: Vec<(&String, &Website)> = sorted_keys(&self.objects).iter()
+            .filter_map(|identifier| self.objects.get_key_value(identifier))
+            .filter(|(_, website)| query.matches(website))
+            .collect();
+
+        if let Some(key) = &query.sort_key {
+            let mut keyed: Vec<_> = found.into_iter()
+                .map(|(identifier, website)| (website.attributes().remove(key), identifier, website))
+                .collect();
+            keyed.sort_by(|(a, _, _), (b, _, _)| match (a, b) {
+                (Some(a), Some(b)) => compare_values(a, b),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => Ordering::Equal,
+            });
+            found = keyed.into_iter().map(|(_, identifier, website)| (identifier, website)).collect();
+        }
+
+        found
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn website(name: &str, creation_date: Option<(i32, u32, u32)>, keywords: &[&str]) -> Website {
+        Website {
+            creation_date: creation_date.map(|(y, m, d)| NaiveDate::from_ymd_opt(y, m, d).unwrap()),
+            keywords: keywords.iter().map(|keyword| String::from(*keyword)).collect(),
+            ..Website::new(String::from(name), format!("{}.com", name.to_lowercase()), String::new(), String::from("Kamon Ayeva"))
+        }
+    }
+
+    fn prototype() -> Prototype {
+        let mut prototype = Prototype::new();
+        prototype.register(String::from("c"), website("Gamma", Some((2019, 1, 1)), &["python"]));
+        prototype.register(String::from("a"), website("Beta", None, &["data"]));
+        prototype.register(String::from("b"), website("Alpha", Some((2018, 8, 1)), &["python", "data"]));
+        prototype
+    }
+
+    fn identifiers(found: Vec<(&String, &Website)>) -> Vec<String> {
+        found.into_iter().map(|(identifier, _)| identifier.clone()).collect()
+    }
+
+    #[test]
+    fn find_by_attributes() {
+        let prototype = prototype();
+
+        let query = Query::new().equals("name", Value::Text(String::from("Beta")));
+        assert_eq!(identifiers(prototype.find(&query)), vec!["a"]);
+
+        let query = Query::new().contains("domain", "MMA");
+        assert_eq!(identifiers(prototype.find(&query)), vec!["c"]);
+
+        let query = Query::new().contains("keywords", "DAT");
+        assert_eq!(identifiers(prototype.find(&query)), vec!["a", "b"]);
+
+        let query = Query::new().has_keyword("python").has_keyword("data");
+        assert_eq!(identifiers(prototype.find(&query)), vec!["b"]);
+    }
+
+    #[test]
+    fn find_by_typed_text() {
+        let mut prototype = prototype();
+        let website = prototype.objects.get_mut("a").unwrap();
+        website.set("category", Value::Text(String::from("2019"))).unwrap();
+        website.set("articles", Value::Number(120.0)).unwrap();
+        prototype.objects.get_mut("b").unwrap().set("articles", Value::Text(String::from("120"))).unwrap();
+
+        let query = Query::new().equals_text("category", "2019");
+        assert_eq!(identifiers(prototype.find(&query)), vec!["a"]);
+
+        let query = Query::new().equals_text("articles", "120.0");
+        assert_eq!(identifiers(prototype.find(&query)), vec!["a"]);
+
+        let query = Query::new().equals_text("articles", "120");
+        assert_eq!(identifiers(prototype.find(&query)), vec!["a", "b"]);
+
+        let query = Query::new().equals_text("keywords", "python,data");
+        assert_eq!(identifiers(prototype.find(&query)), vec!["b"]);
+
+        let query = Query::new().equals_text("creation_date", "2019-01-01");
+        assert_eq!(identifiers(prototype.find(&query)), vec!["c"]);
+    }
+
+    #[test]
+    fn find_by_creation_date() {
+        let prototype = prototype();
+
+        let query = Query::new().created_between(NaiveDate::from_ymd_opt(2018, 8, 1), None);
+        assert_eq!(identifiers(prototype.find(&query)), vec!["b", "c"]);
+
+        let query = Query::new().created_between(None, NaiveDate::from_ymd_opt(2018, 12, 31));
+        assert_eq!(identifiers(prototype.find(&query)), vec!["b"]);
+    }
+
+    #[test]
+    fn sort_by_key() {
+        let prototype = prototype();
+
+        assert_eq!(identifiers(prototype.find(&Query::new())), vec!["a", "b", "c"]);
+        assert_eq!(identifiers(prototype.find(&Query::new().sort_by("name"))), vec!["b", "a", "c"]);
+        assert_eq!(identifiers(prototype.find(&Query::new().sort_by("creation_date"))), vec!["b", "c", "a"]);
+    }
+}
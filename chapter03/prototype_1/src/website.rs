@@ -0,0 +1,391 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use crate::sorted_keys;
+
+/// Value of an attribute of a website.
+/// It's saved with its type, such as `{"type": "date", "value": "2018-08-01"}`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value", rename_all = "lowercase")]
+pub enum Value {
+    Text(String),
+    Number(f64),
+    List(Vec<String>),
+    Date(NaiveDate),
+}
+
+impl Value {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Text(_) => "text",
+            Value::Number(_) => "number",
+            Value::List(_) => "list",
+            Value::Date(_) => "date",
+        }
+    }
+
+    /// A number or a date if the text can be read as one,
+    /// a list if it has commas, and text otherwise.
+    pub fn infer(text: &str) -> Value {
+        if let Some(number) = parse_number(text) {
+            Value::Number(number)
+        } else if let Ok(date) = NaiveDate::parse_from_str(text, "%Y-%m-%d") {
+            Value::Date(date)
+        } else if text.contains(',') {
+            Value::List(split_list(text))
+        } else {
+            Value::Text(String::from(text))
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Text(text) => write!(f, "{}", text),
+            Value::Number(number) => write!(f, "{}", number),
+            Value::List(items) => write!(f, "{}", items.join(", ")),
+            Value::Date(date) => write!(f, "{}", date.format("%Y-%m-%d")),
+        }
+    }
+}
+
+/// An attribute was given a value of the wrong type.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttributeError {
+    pub key: String,
+    pub expected: &'static str,
+    pub found: &'static str,
+}
+
+impl fmt::Display for AttributeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} must be {}, not {}", self.key, self.expected, self.found)
+    }
+}
+
+/// A clone of a prototype with the attributes overridden in it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Derivation {
+    /// Identifier of the prototype cloned.
+    pub from: String,
+    /// Sorted names of the attributes.
+    pub overridden: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Website {
+    pub name: String,
+    pub domain: String,
+    pub description: String,
+    pub author: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub creation_date: Option<NaiveDate>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub keywords: Vec<String>,
+    /// Other attributes such as "technologies".
+    /// Once an extension is set, it keeps the type of its first value.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub extensions: HashMap<String, Value>,
+    /// Clones which led to this website, the oldest first.
+    /// It's not an attribute, so `get_info` and `diff` ignore it.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub provenance: Vec<Derivation>,
+}
+
+impl Website {
+    pub fn new(name: String, domain: String, description: String, author: String) -> Website {
+        Website {
+            name,
+            domain,
+            description,
+            author,
+            category: None,
+            creation_date: None,
+            keywords: Vec::new(),
+            extensions: HashMap::new(),
+            provenance: Vec::new(),
+        }
+    }
+
+    /// Set an attribute by its name, checking the type of the value.
+    /// Numbers must be finite, since others cannot be saved in JSON.
+    pub fn set(&mut self, key: &str, value: Value) -> Result<(), AttributeError> {
+        let mismatch = |expected: &'static str, value: &Value| AttributeError {
+            key: String::from(key),
+            expected,
+            found: value.type_name(),
+        };
+
+        if let Value::Number(number) = value {
+            if !number.is_finite() {
+                return Err(AttributeError {
+                    key: String::from(key),
+                    expected: "finite number",
+                    found: "infinite or NaN",
+                });
+            }
+        }
+
+        match (key, value) {
+            ("name", Value::Text(text)) => self.name = text,
+            ("domain", Value::Text(text)) => self.domain = text,
+            ("description", Value::Text(text)) => self.description = text,
+            ("author", Value::Text(text)) => self.author = text,
+            ("category", Value::Text(text)) => self.category = Some(text),
+            ("creation_date", Value::Date(date)) => self.creation_date = Some(date),
+            ("keywords", Value::List(items)) => self.keywords = items,
+            ("name", value) | ("domain", value) | ("description", value) | ("author", value) | ("category", value) =>
+                return Err(mismatch("text", &value)),
+            ("creation_date", value) => return Err(mismatch("date", &value)),
+            ("keywords", value) => return Err(mismatch("list", &value)),
+            (_, value) => {
+                if let Some(current) = self.extensions.get(key) {
+                    if current.type_name() != value.type_name() {
+                        return Err(mismatch(current.type_name(), &value));
+                    }
+                }
+                self.extensions.insert(String::from(key), value);
+            },
+        }
+
+        Ok(())
+    }
+
+    /// Unset an attribute which can be left out.
+    /// An unset author is empty, and the name, domain and description cannot be unset.
+    pub fn unset(&mut self, key: &str) -> Result<(), AttributeError> {
+        match key {
+            "name" | "domain" | "description" => return Err(AttributeError {
+                key: String::from(key),
+                expected: "text",
+                found: "nothing",
+            }),
+            "author" => self.author = String::new(),
+            "category" => self.category = None,
+            "creation_date" => self.creation_date = None,
+            "keywords" => self.keywords = Vec::new(),
+            _ => {
+                self.extensions.remove(key);
+            },
+        }
+
+        Ok(())
+    }
+
+    /// Read a value typed on the command line for an attribute.
+    ///
+    /// Lists are separated by commas and dates are in YYYY-MM-DD.
+    /// The type of a new extension is guessed by `Value::infer`.
+    pub fn parse_value(&self, key: &str, text: &str) -> Result<Value, AttributeError> {
+        let expected = match key {
+            "name" | "domain" | "description" | "author" | "category" => "text",
+            "creation_date" => "date",
+            "keywords" => "list",
+            _ => match self.extensions.get(key) {
+                Some(current) => current.type_name(),
+                None => return Ok(Value::infer(text)),
+            },
+        };
+
+        let invalid = || AttributeError {
+            key: String::from(key),
+            expected,
+            found: "text",
+        };
+        match expected {
+            "number" => parse_number(text).map(Value::Number).ok_or_else(invalid),
+            "date" => NaiveDate::parse_from_str(text, "%Y-%m-%d").map(Value::Date).map_err(|_| invalid()),
+            "list" => Ok(Value::List(split_list(text))),
+            _ => Ok(Value::Text(String::from(text))),
+        }
+    }
+
+    /// All the attributes which are set, by their names.
+    pub fn attributes(&self) -> HashMap<String, Value> {
+        let mut attributes = self.extensions.clone();
+
+        attributes.insert(String::from("name"), Value::Text(self.name.clone()));
+        attributes.insert(String::from("domain"), Value::Text(self.domain.clone()));
+        attributes.insert(String::from("description"), Value::Text(self.description.clone()));
+        if !self.author.is_empty() {
+            attributes.insert(String::from("author"), Value::Text(self.author.clone()));
+        }
+        if let Some(category) = &self.category {
+            attributes.insert(String::from("category"), Value::Text(category.clone()));
+        }
+        if let Some(creation_date) = self.creation_date {
+            attributes.insert(String::from("creation_date"), Value::Date(creation_date));
+        }
+        if !self.keywords.is_empty() {
+            attributes.insert(String::from("keywords"), Value::List(self.keywords.clone()));
+        }
+
+        attributes
+    }
+
+    /// The latest clone which overrode an attribute.
+    /// `None` if the attribute is from the original prototype.
+    pub fn origin_of(&self, key: &str) -> Option<&Derivation> {
+        self.provenance.iter().rev().find(|derivation| derivation.overridden.iter().any(|overridden| overridden == key))
+    }
+
+    pub fn get_provenance(&self) -> String {
+        let mut summary = String::new();
+
+        for derivation in &self.provenance {
+            summary.push_str(&format!("Cloned from {}: {}\n", derivation.from, derivation.overridden.join(", ")));
+        }
+
+        summary
+    }
+
+    pub fn get_info(&self) -> String {
+        let mut summary = vec![format!("Website: \"{}\"\n", self.name)];
+
+        let infos = self.attributes();
+        // HashMap cannot be sorted like Python.
+        // So, get sorted keys of it separately and print infos orderly.
+        // Reference: https://doc.rust-lang.org/std/collections/struct.HashMap.html#method.iter
+        let sorted_keys = sorted_keys(&infos);
+
+        for key in sorted_keys {
+            if key == "name" {
+                continue
+            }
+            summary.push(format!("{}: {}\n", key, infos[&key]));
+        }
+
+        let mut summary_for_print = String::new();
+
+        for entry in summary {
+            summary_for_print.push_str(&entry);
+        }
+
+        summary_for_print
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    Added(String, Value),
+    Removed(String, Value),
+    Changed(String, Value, Value),
+}
+
+/// Differences of the attributes from `a` to `b`, sorted by the names like `get_info`.
+pub fn diff(a: &Website, b: &Website) -> Vec<Change> {
+    let a = a.attributes();
+    let b = b.attributes();
+
+    let mut keys = sorted_keys(&a);
+    keys.extend(sorted_keys(&b).into_iter().filter(|key| !a.contains_key(key)));
+    keys.sort();
+
+    keys.into_iter()
+        .filter_map(|key| match (a.get(&key), b.get(&key)) {
+            (Some(old), Some(new)) if old != new => Some(Change::Changed(key, old.clone(), new.clone())),
+            (Some(old), None) => Some(Change::Removed(key, old.clone())),
+            (None, Some(new)) => Some(Change::Added(key, new.clone())),
+            _ => None,
+        })
+        .collect()
+}
+
+/// One line for each change, such as `~ domain: a.com -> b.com`.
+pub fn get_diff_info(changes: &[Change]) -> String {
+    let mut summary = String::new();
+
+    for change in changes {
+        let line = match change {
+            Change::Added(key, new) => format!("+ {}: {}\n", key, new),
+            Change::Removed(key, old) => format!("- {}: {}\n", key, old),
+            Change::Changed(key, old, new) => format!("~ {}: {} -> {}\n", key, old, new),
+        };
+        summary.push_str(&line);
+    }
+
+    summary
+}
+
+fn split_list(text: &str) -> Vec<String> {
+    text.split(',')
+        .map(|item| item.trim())
+        .filter(|item| !item.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// Only finite numbers, which can be saved in JSON.
+fn parse_number(text: &str) -> Option<f64> {
+    text.parse().ok().filter(|number: &f64| number.is_finite())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn website() -> Website {
+        Website {
+            category: Some(String::from("Blog")),
+            creation_date: NaiveDate::from_ymd_opt(2018, 8, 1),
+            keywords: vec![String::from("python")],
+            ..Website::new(String::from("A"), String::from("a.com"), String::from("About A"), String::from("Kamon Ayeva"))
+        }
+    }
+
+    #[test]
+    fn unset_optional_attributes() {
+        let mut website = website();
+        website.set("articles", Value::Number(3.0)).unwrap();
+
+        for key in &["author", "category", "creation_date", "keywords", "articles", "missing"] {
+            website.unset(key).unwrap();
+        }
+        assert_eq!(sorted_keys(&website.attributes()), vec!["description", "domain", "name"]);
+        assert_eq!(website.get_info(), "Website: \"A\"\ndescription: About A\ndomain: a.com\n");
+
+        let error = website.unset("domain").unwrap_err();
+        assert_eq!(error.to_string(), "domain must be text, not nothing");
+        assert_eq!(website.domain, "a.com");
+    }
+
+    #[test]
+    fn reject_non_finite_numbers() {
+        let mut website = website();
+
+        for number in &[f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            let error = website.set("articles", Value::Number(*number)).unwrap_err();
+            assert_eq!(error.to_string(), "articles must be finite number, not infinite or NaN");
+        }
+        assert!(website.extensions.is_empty());
+
+        assert!(website.parse_value("articles", "NaN").is_ok_and(|value| value == Value::Text(String::from("NaN"))));
+        website.set("articles", Value::Number(1.0)).unwrap();
+        assert!(website.parse_value("articles", "inf").is_err());
+    }
+
+    #[test]
+    fn diff_attributes() {
+        let a = website();
+        let mut b = website();
+        b.unset("category").unwrap();
+        b.set("domain", Value::Text(String::from("b.com"))).unwrap();
+        b.set("articles", Value::Number(3.0)).unwrap();
+        b.provenance.push(Derivation { from: String::from("a"), overridden: vec![String::from("domain")] });
+
+        let changes = diff(&a, &b);
+        assert_eq!(changes, vec![
+            Change::Added(String::from("articles"), Value::Number(3.0)),
+            Change::Removed(String::from("category"), Value::Text(String::from("Blog"))),
+            Change::Changed(String::from("domain"), Value::Text(String::from("a.com")), Value::Text(String::from("b.com"))),
+        ]);
+        assert_eq!(get_diff_info(&changes), "+ articles: 3\n- category: Blog\n~ domain: a.com -> b.com\n");
+        assert!(diff(&a, &a.clone()).is_empty());
+    }
+}
@@ -0,0 +1,95 @@
+// Manage a registry through the binary.
+
+use std::env;
+use std::fs;
+use std::process::{self, Command, Output};
+
+fn run(registry: &str, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_prototype_1"))
+        .args(["--registry", registry])
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+/// Path of a registry unique to the test and the run, which doesn't exist yet.
+fn registry(name: &str) -> String {
+    let filepath = env::temp_dir().join(format!("prototype_1_cli_{}_{}.json", process::id(), name));
+    let _ = fs::remove_file(&filepath);
+    filepath.to_str().unwrap().to_string()
+}
+
+#[test]
+fn register_list_show_and_unregister() {
+    let registry = registry("register");
+
+    let output = run(&registry, &["register", "cg", "ContentGardening", "contentgardening.com", "Automation", "Kamon Ayeva",
+                                  "keywords=python, data", "creation_date=2018-08-01", "articles=120"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+
+    assert_eq!(stdout(&run(&registry, &["list"])), "cg: ContentGardening\n");
+    let shown = stdout(&run(&registry, &["show", "cg"]));
+    assert!(shown.contains("articles: 120\n"));
+    assert!(shown.contains("creation_date: 2018-08-01\n"));
+    assert!(shown.contains("keywords: python, data\n"));
+
+    assert!(run(&registry, &["unregister", "cg"]).status.success());
+    assert_eq!(stdout(&run(&registry, &["list"])), "");
+    assert_eq!(run(&registry, &["unregister", "cg"]).status.code(), Some(1));
+    fs::remove_file(&registry).unwrap();
+}
+
+#[test]
+fn clone_diff_and_find() {
+    let registry = registry("clone");
+    run(&registry, &["register", "cg", "ContentGardening", "contentgardening.com", "Automation", "Kamon Ayeva", "category=Blog"]);
+
+    let output = run(&registry, &["clone", "cg", "--as", "play", "--unset", "category", "name=Playground"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(stdout(&run(&registry, &["show", "play"])).ends_with("Cloned from cg: category, name\n"));
+
+    assert_eq!(
+        stdout(&run(&registry, &["diff", "cg", "play"])),
+        "- category: Blog\n~ name: ContentGardening -> Playground\n"
+    );
+    assert_eq!(stdout(&run(&registry, &["find", "--contains", "name=play"])), "play: Playground\n");
+
+    let output = run(&registry, &["clone", "cg", "creation_date=yesterday"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).contains("creation_date must be date, not text"));
+    fs::remove_file(&registry).unwrap();
+}
+
+#[test]
+fn usage_and_registry_errors() {
+    let registry = registry("errors");
+
+    assert_eq!(run(&registry, &["show"]).status.code(), Some(2));
+    assert_eq!(run(&registry, &["show", "missing"]).status.code(), Some(1));
+
+    fs::write(&registry, "not json").unwrap();
+    let output = run(&registry, &["list"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).starts_with("Invalid registry: "));
+    fs::remove_file(&registry).unwrap();
+}
+
+#[test]
+fn find_where_with_attribute_types() {
+    let registry = registry("where");
+    run(&registry, &["register", "a", "A", "a.com", "About A", "Kamon Ayeva", "category=2019", "articles=120"]);
+    run(&registry, &["register", "b", "B", "b.com", "About B", "Kamon Ayeva", "category=Blog"]);
+
+    assert_eq!(stdout(&run(&registry, &["find", "--where", "category=2019"])), "a: A\n");
+    assert_eq!(stdout(&run(&registry, &["find", "--where", "articles=120.0"])), "a: A\n");
+    assert_eq!(stdout(&run(&registry, &["find", "--where", "category=Blog"])), "b: B\n");
+    fs::remove_file(&registry).unwrap();
+}
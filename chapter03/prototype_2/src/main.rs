@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::rc::Rc;
 
 // Since there's no feature like '**kwargs' of Python in Rust,
 // and additional fields cannot be add to defined struct,
@@ -20,7 +21,7 @@ impl Website {
         default_values.insert(String::from("author"), author);
 
         for (key, value) in &add_info {
-            default_values.insert(String::from(key.clone()), value.clone());
+            default_values.insert(key.clone(), value.clone());
         }
 
         Website { info: default_values }
@@ -36,7 +37,7 @@ impl Website {
         let sorted_keys = sorted_keys(&infos);
 
         for key in sorted_keys {
-            if key == String::from("name") {
+            if key == "name" {
                 continue
             }
             summary.push(format!("{}: {}\n", key, infos[&key]));
@@ -52,34 +53,38 @@ impl Website {
     }
 }
 
-struct Prototype<'a> {
-    objects: HashMap<String, &'a Website>,
+// Prototypes are shared with Rc instead of borrowed,
+// so the registry doesn't depend on the lifetime of the caller's websites.
+struct Prototype {
+    objects: HashMap<String, Rc<Website>>,
 }
 
-impl<'a> Prototype<'a> {
-    fn new() -> Prototype<'a> {
+impl Prototype {
+    fn new() -> Prototype {
         Prototype {
             objects: HashMap::new(),
         }
     }
 
-    fn register(&mut self, identifier: String, obj: &'a Website) {
+    fn register(&mut self, identifier: String, obj: Rc<Website>) {
         self.objects.insert(identifier, obj);
     }
 
-    fn unregister(&mut self, identifier: String) {
-        self.objects.remove(&identifier);
+    fn unregister(&mut self, identifier: &str) -> Option<Rc<Website>> {
+        self.objects.remove(identifier)
     }
 
-    fn clone(&mut self, identifier: String, new_infos: HashMap<String, String>) -> Result<Website, String>  {
-        let found = self.objects.get(&identifier);
+    /// The clone shares the data with the prototype until it's modified.
+    /// Only the clones with new infos are copied, by `Rc::make_mut`.
+    fn clone(&self, identifier: &str, new_infos: HashMap<String, String>) -> Result<Rc<Website>, String>  {
+        let found = self.objects.get(identifier);
 
         match found {
             Some(current_obj) => {
-                let mut new_obj = (*current_obj).clone();
+                let mut new_obj = Rc::clone(current_obj);
 
                 for (new_key, new_value) in new_infos {
-                    new_obj.info.insert(new_key.clone(), new_value.clone());
+                    Rc::make_mut(&mut new_obj).info.insert(new_key, new_value);
                 }
 
                 Ok(new_obj)
@@ -103,7 +108,8 @@ fn sorted_keys(hashmap: &HashMap<String, String>) -> Vec<String> {
     keys
 }
  
-fn main() {
+/// Register site1 and clone it as site2 with additional infos.
+fn register_and_clone() -> (Prototype, Rc<Website>, Rc<Website>) {
     // Since HashMap can have the type of value specified in advance,
     // keywords will be set as String comma-breaked.
     let keywords: String = String::from("python,data,apis,automation");
@@ -111,16 +117,16 @@ fn main() {
     keywords_mapped.insert(String::from("keywords"), keywords);
     keywords_mapped.insert(String::from("category"), String::from("Blog"));
 
-    let site1 = Website::new("ContentGardening".to_string(),
+    let site1 = Rc::new(Website::new("ContentGardening".to_string(),
         "contentgardening.com".to_string(),
         "Automation and data-driven apps".to_string(),
         "Kamon Ayeva".to_string(),
         keywords_mapped
-    );
+    ));
 
     let mut prototype = Prototype::new();
     let identifier = "ka-cg-1".to_string();
-    prototype.register(identifier, &site1);
+    prototype.register(identifier, Rc::clone(&site1));
 
     // Prepare to clone site1 with addtional infos
     let mut site2_add_info: HashMap<String, String> = HashMap::new();
@@ -131,12 +137,70 @@ fn main() {
     site2_add_info.insert("creation_date".to_string(), "2018-08-01".to_string());
 
     // Clone site1 with addtional infos.
-    let site2 = prototype.clone("ka-cg-1".to_string(), site2_add_info).unwrap();
+    let site2 = prototype.clone("ka-cg-1", site2_add_info).unwrap();
+
+    // The registry is returned with the websites, which it doesn't borrow.
+    (prototype, site1, site2)
+}
+
+fn main() {
+    let (mut prototype, site1, site2) = register_and_clone();
 
-    println!("{}", prototype.objects[&"ka-cg-1".to_string()].get_info());
+    println!("{}", prototype.objects["ka-cg-1"].get_info());
     println!("{}", site2.get_info());
 
     // https://stackoverflow.com/questions/30157258/does-rust-track-unique-object-ids-and-can-we-print-them
-    println!("Address of site1 : {:p} != Address of site2 : {:p}", &site1, &site2);
-    println!("Address of site1 : {:p}  = Address of site1 in prototype : {:p}", &site1, prototype.objects[&"ka-cg-1".to_string()]);
+    println!("Address of site1 : {:p} != Address of site2 : {:p}", Rc::as_ptr(&site1), Rc::as_ptr(&site2));
+    println!("Address of site1 : {:p}  = Address of site1 in prototype : {:p}", Rc::as_ptr(&site1), Rc::as_ptr(&prototype.objects["ka-cg-1"]));
+
+    // A clone without new infos shares site1 until it's modified.
+    let mut site3 = prototype.clone("ka-cg-1", HashMap::new()).unwrap();
+    println!("Address of site1 : {:p}  = Address of site3 : {:p}", Rc::as_ptr(&site1), Rc::as_ptr(&site3));
+    Rc::make_mut(&mut site3).info.insert("category".to_string(), "Archive".to_string());
+    println!("Address of site1 : {:p} != Address of modified site3 : {:p}", Rc::as_ptr(&site1), Rc::as_ptr(&site3));
+
+    // site1 is still available after it's unregistered.
+    prototype.unregister("ka-cg-1");
+    println!("References to site1 after unregistering : {}", Rc::strong_count(&site1));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clone_with_new_infos_is_copied() {
+        let (prototype, site1, site2) = register_and_clone();
+
+        assert!(!Rc::ptr_eq(&site1, &site2));
+        assert_eq!(site1.info["name"], "ContentGardening");
+        assert_eq!(site2.info["name"], "ContentGardeningPlayground");
+        assert_eq!(site2.info["keywords"], "python,data,apis,automation");
+        assert!(Rc::ptr_eq(&site1, &prototype.objects["ka-cg-1"]));
+    }
+
+    #[test]
+    fn clone_is_shared_until_modified() {
+        let (prototype, site1, _) = register_and_clone();
+
+        let mut site3 = prototype.clone("ka-cg-1", HashMap::new()).unwrap();
+        assert!(Rc::ptr_eq(&site1, &site3));
+
+        Rc::make_mut(&mut site3).info.insert("category".to_string(), "Archive".to_string());
+        assert!(!Rc::ptr_eq(&site1, &site3));
+        assert_eq!(site1.info["category"], "Blog");
+        assert_eq!(site3.info["category"], "Archive");
+    }
+
+    #[test]
+    fn unregistered_prototype_is_kept_by_others() {
+        let (mut prototype, site1, _) = register_and_clone();
+        assert_eq!(Rc::strong_count(&site1), 2);
+
+        let removed = prototype.unregister("ka-cg-1").unwrap();
+        assert!(Rc::ptr_eq(&site1, &removed));
+        drop(removed);
+        assert_eq!(Rc::strong_count(&site1), 1);
+        assert!(prototype.clone("ka-cg-1", HashMap::new()).is_err());
+    }
 }